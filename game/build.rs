@@ -93,6 +93,10 @@ fn collect_levels(dir: &Path, prefix: &str, levels: &mut Vec<String>) {
         let path = entry.path();
         if path.is_dir() {
             let subdir_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            // Directories starting with an underscore hold editor data such as stamps, not levels
+            if subdir_name.starts_with('_') {
+                continue;
+            }
             let new_prefix = if prefix.is_empty() {
                 subdir_name.to_string()
             } else {
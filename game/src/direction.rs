@@ -1,6 +1,6 @@
 use crate::position::PositionDelta;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum Dir4 {
     North,
     South,
@@ -44,9 +44,27 @@ impl Dir4 {
             Dir4::East => Dir4::North,
         }
     }
+
+    /// Mirror left-right.
+    pub(crate) fn flip_x(self) -> Dir4 {
+        match self {
+            Dir4::East => Dir4::West,
+            Dir4::West => Dir4::East,
+            dir => dir,
+        }
+    }
+
+    /// Mirror top-bottom.
+    pub(crate) fn flip_y(self) -> Dir4 {
+        match self {
+            Dir4::North => Dir4::South,
+            Dir4::South => Dir4::North,
+            dir => dir,
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum Dir8 {
     North,
     South,
@@ -122,4 +140,22 @@ impl Dir8 {
         delta.dx = 0;
         Self::from_delta(delta)
     }
+
+    /// Mirror left-right.
+    pub(crate) fn flip_x(self) -> Self {
+        let PositionDelta { dx, dy } = self.delta();
+        Self::from_delta(PositionDelta::new(-dx, dy)).unwrap()
+    }
+
+    /// A quarter turn clockwise.
+    pub(crate) fn rotate_cw(self) -> Self {
+        let PositionDelta { dx, dy } = self.delta();
+        Self::from_delta(PositionDelta::new(-dy, dx)).unwrap()
+    }
+
+    /// Mirror top-bottom.
+    pub(crate) fn flip_y(self) -> Self {
+        let PositionDelta { dx, dy } = self.delta();
+        Self::from_delta(PositionDelta::new(dx, -dy)).unwrap()
+    }
 }
@@ -1,18 +1,75 @@
 use std::collections::HashSet;
-use std::fs::{create_dir_all, read_to_string, write};
+use std::fs::{create_dir_all, write};
+use std::io;
 use std::path::Path;
-use std::str;
 
 use macroquad::prelude::*;
 
 use crate::direction::Dir4;
-use crate::game::{Action, Game, PlayState};
+use crate::game::{Action, Game, PlayState, Solver};
 use crate::grid::{Cell, Grid, LevelMetadata};
 use crate::position::{Position, PositionDelta};
 use crate::sprites::Sprites;
 
+mod autosave;
+mod debugger;
+mod edit_cursor;
+mod heatmap;
+mod hot_reload;
+mod metadata;
+mod paint;
+mod portal_dialog;
+mod solver_panel;
+mod stamps;
+mod symmetry;
+mod text_input;
+mod timeline;
+mod viewport;
+mod wiring;
+
+use autosave::Recovery;
+use debugger::Debugger;
+use heatmap::Heatmap;
+use hot_reload::LevelFileTimes;
+use metadata::MetadataDialog;
+use paint::{PaintMode, Shape};
+use portal_dialog::PortalDialog;
+use stamps::Stamp;
+use symmetry::Symmetry;
+use text_input::{TextInput, TextInputEvent};
+use timeline::TIMELINE_HEIGHT;
+use viewport::set_clip;
+use wiring::Wiring;
+
 const PADDING: f32 = 8.0;
 const TOOLBAR_WIDTH: f32 = 120.0;
+const LEVELS_DIR: &str = "levels";
+
+fn level_csv_path(level: &str) -> String {
+    format!("{}/{}.csv", LEVELS_DIR, level)
+}
+
+fn level_json_path(level: &str) -> String {
+    format!("{}/{}.json", LEVELS_DIR, level)
+}
+
+/// Grid for a level that doesn't exist yet: empty, with the player in the middle.
+fn new_level_grid() -> Grid {
+    let mut grid = Grid::create_empty(10, 10);
+    *grid.at_mut(Position::new(5, 5)) = Cell::Player(Dir4::South);
+    grid
+}
+
+/// Write a level's CSV and JSON files, creating parent directories as needed.
+fn write_level(level: &str, grid: &Grid, metadata: &LevelMetadata) -> io::Result<()> {
+    let csv_path = level_csv_path(level);
+    if let Some(parent) = Path::new(&csv_path).parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(&csv_path, grid.to_csv())?;
+    write(level_json_path(level), metadata.to_json())
+}
 
 #[derive(Clone, Copy)]
 struct Rect {
@@ -42,10 +99,11 @@ enum Tool {
     BlackHole,
     Explosive,
     Trigger,
+    Stamp,
 }
 
 impl Tool {
-    fn all() -> [Tool; 12] {
+    fn all() -> [Tool; 13] {
         [
             Tool::Move,
             Tool::Wall,
@@ -59,6 +117,7 @@ impl Tool {
             Tool::BlackHole,
             Tool::Explosive,
             Tool::Trigger,
+            Tool::Stamp,
         ]
     }
 
@@ -76,6 +135,7 @@ impl Tool {
             Tool::BlackHole => "Hole",
             Tool::Explosive => "Bomb",
             Tool::Trigger => "Trigger",
+            Tool::Stamp => "Stamp",
         }
     }
 
@@ -93,12 +153,18 @@ impl Tool {
             Tool::BlackHole => "o",
             Tool::Explosive => "x",
             Tool::Trigger => "t",
+            Tool::Stamp => "l",
         }
     }
 
+    /// Tools that use the plain mouse wheel, leaving zoom to Ctrl+wheel.
+    fn uses_wheel(self) -> bool {
+        matches!(self, Tool::Player | Tool::Trigger | Tool::Stamp)
+    }
+
     fn to_cell(self, pos: Position, player_dir: Dir4, trigger_digit: u8) -> Option<Cell> {
         match self {
-            Tool::Move | Tool::Portal | Tool::Note => None,
+            Tool::Move | Tool::Portal | Tool::Note | Tool::Stamp => None,
             Tool::Wall => Some(Cell::Wall),
             Tool::Player => Some(Cell::Player(player_dir)),
             Tool::Rat => Some(Cell::Rat(pos.direction_to(Position::new(0, 0)))),
@@ -112,61 +178,250 @@ impl Tool {
     }
 }
 
+fn mouse_over_toolbar() -> bool {
+    mouse_position().0 < TOOLBAR_WIDTH
+}
+
+/// Toolbar button with its label centered, highlighted while `active`.
+fn toggle_button(rect: Rect, label: &str, active: bool) {
+    let bg_color = if active {
+        Color::from_rgba(80, 80, 100, 255)
+    } else {
+        Color::from_rgba(50, 50, 60, 255)
+    };
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, bg_color);
+    let dims = measure_text(label, None, 22, 1.0);
+    draw_text(
+        label,
+        rect.x + (rect.w - dims.width) / 2.0,
+        rect.y + (rect.h + dims.offset_y) / 2.0,
+        22.0,
+        WHITE,
+    );
+}
+
+/// Wheel movement for the grid and tools, which is left to the toolbar while the mouse is over it.
+fn grid_wheel() -> f32 {
+    if mouse_over_toolbar() {
+        0.0
+    } else {
+        mouse_wheel().1
+    }
+}
+
 /// An item being dragged as part of a bulk selection
 struct DraggedItem {
     delta: PositionDelta,
     cell: Cell,
     portal: Option<String>,
+    /// Levels the portal requires (see `Grid::portal_requires`)
+    requires: Vec<String>,
     note: Option<String>,
 }
 
+impl DraggedItem {
+    /// Lift the cell, portal and note at `pos` out of `grid`. None if there's nothing there.
+    fn take(grid: &mut Grid, pos: Position, delta: PositionDelta) -> Option<Self> {
+        let item = Self {
+            delta,
+            cell: grid.at(pos),
+            portal: grid.get_portal(pos).map(String::from),
+            requires: grid.portal_requires(pos).to_vec(),
+            note: grid.get_note(pos).map(String::from),
+        };
+        if matches!(item.cell, Cell::Empty) && item.portal.is_none() && item.note.is_none() {
+            return None;
+        }
+        *grid.at_mut(pos) = Cell::Empty;
+        grid.remove_portal(pos);
+        grid.remove_note(pos);
+        Some(item)
+    }
+
+    /// Put the portal and note down at `target`, returning the cell for the caller to place.
+    fn put_down(self, grid: &mut Grid, target: Position) -> Cell {
+        if let Some(level) = self.portal {
+            grid.insert_portal(target, level);
+            grid.set_portal_requires(target, self.requires);
+        }
+        if let Some(text) = self.note {
+            grid.insert_note(target, text);
+        }
+        self.cell
+    }
+}
+
 struct Editor {
     initial_grid: Grid,
     input_history: Vec<Action>,
     game: Game,
     tool: Tool,
-    player_dir: Dir4,                          // Direction for placing new players
-    trigger_digit: u8,                         // Current digit for Trigger tool (1-9)
-    portal_dialog: Option<(Position, String)>, // (position, current text) when entering portal level
-    note_dialog: Option<(Position, String)>,   // (position, current text) when entering note text
+    paint_mode: PaintMode,
+    symmetry: Symmetry,
+    player_dir: Dir4,                           // Direction for placing new players
+    trigger_digit: u8,                          // Current digit for Trigger tool (1-9)
+    portal_dialog: Option<PortalDialog>,        // Portal target entry with completions
+    note_dialog: Option<(Position, TextInput)>, // (position, field) for note text
+    // Level path under `levels/` (without extension), used for file names and portal checks
+    level_path: String,
+    // Display name saved in the level's JSON, edited through the metadata panel
+    level_name: String,
+    metadata_dialog: Option<MetadataDialog>,
     sprites: Sprites,
     dragging: Option<(Position, Cell)>, // Source position and cell being dragged
     last_paint_pos: Option<Position>,   // Last position painted/erased (for drag painting)
+    // Line or rectangle being dragged out: (shape, start, current end)
+    shape_drag: Option<(Shape, Position, Position)>,
     // Rectangle selection state
     selecting_rect: Option<(Position, Position)>, // (start, current) corners while dragging
     selection: HashSet<Position>,                 // Currently selected cell positions
     // Multi-item dragging state (anchor_pos, items with offsets from anchor)
     dragging_selection: Option<(Position, Vec<DraggedItem>)>,
+    // Solver panel state: search for the current initial grid, and next solution to load
+    solver_enabled: bool,
+    solver: Option<Solver>,
+    solution_index: usize,
+    // Reference solution saved with the level, and whether it still wins after edits
+    saved_solution: Option<Vec<Action>>,
+    saved_solution_wins: bool,
+    // Resolution debugger: move keys trace one move phase by phase instead of playing it
+    debug_mode: bool,
+    debugger: Option<Debugger>,
+    // Cyborg distance overlay for the right pane
+    heatmap_enabled: bool,
+    heatmap: Option<Heatmap>,
+    // Trigger and portal overlay for the left pane, with the player's completed levels
+    wiring_enabled: bool,
+    wiring: Option<Wiring>,
+    completed_levels: HashSet<String>,
+    // Viewport: zoom relative to fitting the whole grid, and pan from the pane center in pixels
+    zoom: f32,
+    pan: Vec2,
+    pan_anchor: Option<(f32, f32)>, // Last mouse position while middle-dragging
+    minimap_dragging: bool,
+    toolbar_scroll: f32,
+    // Moves applied in the right pane, or None to follow the end of the history
+    timeline_cursor: Option<usize>,
+    timeline_dragging: bool,
+    // Keyboard editing: grid cursor, where a Shift+arrow selection started, and whether the
+    // current drag or shape was started with Enter (so it ends with Enter, not a mouse release)
+    edit_cursor: Option<Position>,
+    cursor_anchor: Option<Position>,
+    keyboard_drag: bool,
+    // Stamp library from `levels/_stamps/`: the selected stamp, its orientation
+    // (quarter turns clockwise after mirroring) and the name entry for saving a new one
+    stamps: Vec<Stamp>,
+    stamp_index: Option<usize>,
+    stamp_turns: u8,
+    stamp_mirror: bool,
+    stamp_dialog: Option<TextInput>,
+    // Level files on disk as last seen, to reload them when they change, and the level as
+    // last loaded from or saved to them, to tell whether there are unsaved edits
+    level_file_times: LevelFileTimes,
+    last_file_check: f64,
+    level_file_state: Option<Recovery>,
+    // Last failed save, reload or autosave, shown until the next success
+    file_error: Option<String>,
+    // Autosave: state last written to the level or recovery file, and a recovered session to offer
+    saved_state: Option<Recovery>,
+    last_autosave: f64,
+    recovery_dialog: Option<Recovery>,
 }
 
 impl Editor {
-    fn new(grid: Grid, sprites: Sprites) -> Self {
+    fn new(grid: Grid, level_path: String, level_name: String, sprites: Sprites) -> Self {
         let game = Game::new(grid.clone(), HashSet::new());
+        let level_file_times = LevelFileTimes::read(&level_path);
         Self {
             initial_grid: grid,
             input_history: Vec::new(),
             game,
             tool: Tool::Move,
+            paint_mode: PaintMode::Brush,
+            symmetry: Symmetry::Off,
             player_dir: Dir4::South,
             trigger_digit: 1,
             portal_dialog: None,
             note_dialog: None,
+            level_path,
+            level_name,
+            metadata_dialog: None,
             sprites,
             dragging: None,
             last_paint_pos: None,
+            shape_drag: None,
             selecting_rect: None,
             selection: HashSet::new(),
             dragging_selection: None,
+            solver_enabled: false,
+            solver: None,
+            solution_index: 0,
+            saved_solution: None,
+            saved_solution_wins: false,
+            debug_mode: false,
+            debugger: None,
+            heatmap_enabled: false,
+            heatmap: None,
+            wiring_enabled: false,
+            wiring: None,
+            completed_levels: HashSet::new(),
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+            pan_anchor: None,
+            minimap_dragging: false,
+            toolbar_scroll: 0.0,
+            timeline_cursor: None,
+            timeline_dragging: false,
+            edit_cursor: None,
+            cursor_anchor: None,
+            keyboard_drag: false,
+            stamps: Vec::new(),
+            stamp_index: None,
+            stamp_turns: 0,
+            stamp_mirror: false,
+            stamp_dialog: None,
+            level_file_times,
+            last_file_check: 0.0,
+            level_file_state: None,
+            file_error: None,
+            saved_state: None,
+            last_autosave: 0.0,
+            recovery_dialog: None,
         }
     }
 
     fn replay_inputs(&mut self) {
-        self.game = Game::new(self.initial_grid.clone(), HashSet::new());
-        for &input in &self.input_history {
-            if self.game.state.play_state() == PlayState::Playing {
-                self.game.apply_action(input);
+        self.game = Self::replay(&self.initial_grid, &self.input_history);
+        self.debugger = None;
+        if self
+            .timeline_cursor
+            .is_some_and(|index| index >= self.input_history.len())
+        {
+            self.timeline_cursor = None;
+        }
+        self.saved_solution_wins = self.saved_solution.as_ref().is_some_and(|solution| {
+            Self::replay(&self.initial_grid, solution)
+                .state
+                .play_state()
+                == PlayState::Won
+        });
+    }
+
+    fn replay(initial_grid: &Grid, inputs: &[Action]) -> Game {
+        let mut game = Game::new(initial_grid.clone(), HashSet::new());
+        for &input in inputs {
+            if game.state.play_state() == PlayState::Playing {
+                game.apply_action(input);
             }
         }
+        game
+    }
+
+    /// Start from a saved reference solution: show it in the move history and keep verifying it.
+    fn load_saved_solution(&mut self, solution: Vec<Action>) {
+        self.input_history = solution.clone();
+        self.saved_solution = Some(solution);
+        self.replay_inputs();
     }
 
     fn add_input(&mut self, input: Action) {
@@ -187,7 +442,7 @@ impl Editor {
         let grid = if pane == 0 {
             &self.initial_grid
         } else {
-            &self.game.state.grid
+            self.shown_grid()
         };
 
         // Check for portal/note first (they overlay cells)
@@ -221,6 +476,12 @@ impl Editor {
     }
 
     fn place_cell(&mut self, pos: Position, cell: Cell) {
+        self.put_cell_symmetric(pos, cell);
+        self.replay_inputs();
+    }
+
+    /// Set a cell in the initial grid without replaying moves.
+    fn put_cell(&mut self, pos: Position, cell: Cell) {
         // If placing player, remove existing player first
         if matches!(cell, Cell::Player(_)) {
             let players: Vec<_> = self
@@ -235,13 +496,14 @@ impl Editor {
         }
 
         *self.initial_grid.at_mut(pos) = cell;
-        self.replay_inputs();
     }
 
     fn erase_cell(&mut self, pos: Position) {
-        *self.initial_grid.at_mut(pos) = Cell::Empty;
-        self.initial_grid.remove_portal(pos);
-        self.initial_grid.remove_note(pos);
+        for (image, _, _) in self.symmetric_positions(pos) {
+            *self.initial_grid.at_mut(image) = Cell::Empty;
+            self.initial_grid.remove_portal(image);
+            self.initial_grid.remove_note(image);
+        }
         self.replay_inputs();
     }
 
@@ -255,26 +517,23 @@ impl Editor {
         self.replay_inputs();
     }
 
+    /// Open the note dialog, pre-filled with the existing text if there is one.
+    fn open_note_dialog(&mut self, pos: Position) {
+        let current = self.initial_grid.get_note(pos).unwrap_or("");
+        self.note_dialog = Some((pos, TextInput::new(current, true)));
+    }
+
     fn start_drag(&mut self, pos: Position) {
         // If clicking on a selected cell, drag the entire selection
         if self.selection.contains(&pos) {
             let mut items = Vec::new();
             for &sel_pos in &self.selection {
-                let cell = self.initial_grid.at(sel_pos);
-                let portal = self.initial_grid.get_portal(sel_pos).map(String::from);
-                let note = self.initial_grid.get_note(sel_pos).map(String::from);
                 // Include position if it has a non-empty cell, portal, or note
-                if !matches!(cell, Cell::Empty) || portal.is_some() || note.is_some() {
-                    items.push(DraggedItem {
-                        delta: sel_pos - pos,
-                        cell,
-                        portal,
-                        note,
-                    });
-                    *self.initial_grid.at_mut(sel_pos) = Cell::Empty;
-                    self.initial_grid.remove_portal(sel_pos);
-                    self.initial_grid.remove_note(sel_pos);
-                }
+                items.extend(DraggedItem::take(
+                    &mut self.initial_grid,
+                    sel_pos,
+                    sel_pos - pos,
+                ));
             }
             if !items.is_empty() {
                 self.dragging_selection = Some((pos, items));
@@ -300,14 +559,9 @@ impl Editor {
             for item in items {
                 let target = pos + item.delta;
                 if target.in_bounds(bounds) {
-                    if !matches!(item.cell, Cell::Empty) {
-                        self.place_cell(target, item.cell);
-                    }
-                    if let Some(level) = item.portal {
-                        self.initial_grid.insert_portal(target, level);
-                    }
-                    if let Some(text) = item.note {
-                        self.initial_grid.insert_note(target, text);
+                    let cell = item.put_down(&mut self.initial_grid, target);
+                    if !matches!(cell, Cell::Empty) {
+                        self.put_cell(target, cell);
                     }
                 }
             }
@@ -318,7 +572,8 @@ impl Editor {
 
         // Single-item drag
         if let Some((_, cell)) = self.dragging.take() {
-            self.place_cell(pos, cell);
+            self.put_cell(pos, cell);
+            self.replay_inputs();
         }
     }
 
@@ -327,13 +582,8 @@ impl Editor {
         if let Some((anchor, items)) = self.dragging_selection.take() {
             for item in items {
                 let target = anchor + item.delta;
-                *self.initial_grid.at_mut(target) = item.cell;
-                if let Some(level) = item.portal {
-                    self.initial_grid.insert_portal(target, level);
-                }
-                if let Some(text) = item.note {
-                    self.initial_grid.insert_note(target, text);
-                }
+                let cell = item.put_down(&mut self.initial_grid, target);
+                *self.initial_grid.at_mut(target) = cell;
             }
             self.replay_inputs();
             return;
@@ -387,24 +637,21 @@ impl Editor {
     }
 
     fn pane_layout(&self) -> (f32, f32, f32) {
-        let available_width = screen_width() - TOOLBAR_WIDTH - PADDING * 3.0;
+        let available_width = screen_width() - self.panes_x() - PADDING * 2.0;
         let pane_width = available_width / 2.0;
-        let available_height = screen_height() - PADDING * 2.0;
-
-        let cell_w = pane_width / self.initial_grid.width() as f32;
-        let cell_h = available_height / self.initial_grid.height() as f32;
-        let cell_size = cell_w.min(cell_h);
+        let available_height = screen_height() - PADDING * 3.0 - TIMELINE_HEIGHT;
+        let cell_size = self.fit_cell_size() * self.zoom;
 
         (pane_width, available_height, cell_size)
     }
 
     fn grid_offset(&self, pane: usize) -> (f32, f32) {
-        let (pane_width, _, cell_size) = self.pane_layout();
-        let pane_x = TOOLBAR_WIDTH + PADDING + pane as f32 * (pane_width + PADDING);
+        let (pane_width, pane_height, cell_size) = self.pane_layout();
+        let pane_x = self.pane_rect(pane).x;
         let grid_w = self.initial_grid.width() as f32 * cell_size;
         let grid_h = self.initial_grid.height() as f32 * cell_size;
-        let offset_x = pane_x + (pane_width - grid_w) / 2.0;
-        let offset_y = PADDING + (screen_height() - PADDING * 2.0 - grid_h) / 2.0;
+        let offset_x = pane_x + (pane_width - grid_w) / 2.0 + self.pan.x;
+        let offset_y = PADDING + (pane_height - grid_h) / 2.0 + self.pan.y;
         (offset_x, offset_y)
     }
 
@@ -421,6 +668,9 @@ impl Editor {
         let (_, _, cell_size) = self.pane_layout();
 
         for pane in 0..2 {
+            if !self.pane_rect(pane).contains(mx, my) {
+                continue;
+            }
             let (offset_x, offset_y) = self.grid_offset(pane);
 
             let gx = ((mx - offset_x) / cell_size).floor() as i32;
@@ -445,101 +695,112 @@ impl Editor {
         // Draw left pane (initial grid)
         self.render_grid(&self.initial_grid, 0, pane_width, cell_size, "Initial");
 
-        // Draw right pane (game state after replaying)
-        self.render_grid(
-            &self.game.state.grid,
-            1,
-            pane_width,
-            cell_size,
-            "After Moves",
-        );
+        // Draw right pane (game state after replaying, or the phase being debugged)
+        match &self.debugger {
+            Some(debugger) if debugger.grid().is_some() => self.render_grid(
+                debugger.grid().unwrap(),
+                1,
+                pane_width,
+                cell_size,
+                &debugger.label(),
+            ),
+            _ if self.timeline_cursor.is_some() => self.render_grid(
+                self.shown_grid(),
+                1,
+                pane_width,
+                cell_size,
+                &format!(
+                    "After {}/{} Moves",
+                    self.timeline_index(),
+                    self.input_history.len()
+                ),
+            ),
+            _ => self.render_grid(
+                &self.game.state.grid,
+                1,
+                pane_width,
+                cell_size,
+                "After Moves",
+            ),
+        }
+        set_clip(Some(self.pane_rect(1)));
+        self.render_heatmap(cell_size);
+        set_clip(None);
+        self.render_debugger(cell_size);
+
+        // Draw solver results over the right pane
+        self.render_solver_status();
 
         // Draw selection highlights and rectangle
+        set_clip(Some(self.pane_rect(0)));
         self.render_selection(cell_size);
 
-        // Draw cursor preview
-        self.render_cursor_preview(cell_size);
+        // Draw line or rectangle being dragged out
+        self.render_shape_preview(cell_size);
+        self.render_symmetry(cell_size);
+        self.render_wiring(cell_size);
+        self.render_stamp_preview(cell_size);
+        set_clip(None);
+
+        // Draw minimap while zoomed in
+        self.render_minimap();
+
+        // Draw move timeline below the panes
+        self.render_timeline();
 
-        // Draw toolbar
+        // Draw cursor preview, or the keyboard edit cursor
+        if self.edit_cursor.is_some() {
+            self.render_edit_cursor(cell_size);
+        } else {
+            self.render_cursor_preview(cell_size);
+        }
+
+        // Draw toolbar, with the stamp palette beside it while stamping
         self.render_toolbar();
+        self.render_stamp_palette();
 
         // Draw portal dialog if active
         self.render_portal_dialog();
 
         // Draw note dialog if active
         self.render_note_dialog();
-    }
 
-    fn render_portal_dialog(&self) {
-        let Some((_, ref text)) = self.portal_dialog else {
-            return;
-        };
-
-        let dialog_w = 300.0;
-        let dialog_h = 100.0;
-        let dialog_x = (screen_width() - dialog_w) / 2.0;
-        let dialog_y = (screen_height() - dialog_h) / 2.0;
+        // Draw metadata panel if active
+        self.render_metadata_dialog();
 
-        // Dim background
-        draw_rectangle(
-            0.0,
-            0.0,
-            screen_width(),
-            screen_height(),
-            Color::from_rgba(0, 0, 0, 150),
-        );
+        // Draw stamp name entry if active
+        self.render_stamp_dialog();
 
-        // Dialog box
-        draw_rectangle(
-            dialog_x,
-            dialog_y,
-            dialog_w,
-            dialog_h,
-            Color::from_rgba(40, 40, 50, 255),
-        );
-        draw_rectangle_lines(dialog_x, dialog_y, dialog_w, dialog_h, 2.0, WHITE);
+        // Draw recovery offer if a previous session left unsaved work
+        self.render_recovery_dialog();
 
-        // Title
-        draw_text(
-            "Enter level name:",
-            dialog_x + 10.0,
-            dialog_y + 30.0,
-            26.0,
-            WHITE,
-        );
+        // Draw the last file error
+        self.render_file_error();
+    }
 
-        // Text input box
-        let input_x = dialog_x + 10.0;
-        let input_y = dialog_y + 45.0;
-        let input_w = dialog_w - 20.0;
-        let input_h = 30.0;
+    /// Banner over the left pane explaining why the last file operation failed.
+    fn render_file_error(&self) {
+        let Some(error) = &self.file_error else {
+            return;
+        };
+        let rect = self.pane_rect(0);
         draw_rectangle(
-            input_x,
-            input_y,
-            input_w,
-            input_h,
-            Color::from_rgba(20, 20, 30, 255),
-        );
-        draw_rectangle_lines(input_x, input_y, input_w, input_h, 1.0, GRAY);
-        draw_text(text, input_x + 5.0, input_y + 22.0, 22.0, WHITE);
-
-        // Hint
-        draw_text(
-            "Enter to confirm, Esc to cancel",
-            dialog_x + 10.0,
-            dialog_y + 90.0,
-            14.0,
-            GRAY,
+            rect.x,
+            rect.y,
+            rect.w,
+            24.0,
+            Color::from_rgba(80, 20, 20, 220),
         );
+        draw_text(error, rect.x + 6.0, rect.y + 17.0, 18.0, WHITE);
     }
 
     fn render_note_dialog(&self) {
-        let Some((_, ref text)) = self.note_dialog else {
+        let Some((_, ref input)) = self.note_dialog else {
             return;
         };
 
         let dialog_w = 400.0;
-        let dialog_h = 150.0;
+        let dialog_h = 190.0;
         let dialog_x = (screen_width() - dialog_w) / 2.0;
         let dialog_y = (screen_height() - dialog_h) / 2.0;
 
@@ -571,43 +832,20 @@ impl Editor {
             WHITE,
         );
 
-        // Text input box (larger for notes)
-        let input_x = dialog_x + 10.0;
-        let input_y = dialog_y + 45.0;
-        let input_w = dialog_w - 20.0;
-        let input_h = 60.0;
-        draw_rectangle(
-            input_x,
-            input_y,
-            input_w,
-            input_h,
-            Color::from_rgba(20, 20, 30, 255),
+        // Text input box (larger for notes, wraps and scrolls)
+        input.draw(
+            dialog_x + 10.0,
+            dialog_y + 45.0,
+            dialog_w - 20.0,
+            100.0,
+            16.0,
         );
-        draw_rectangle_lines(input_x, input_y, input_w, input_h, 1.0, GRAY);
-
-        // Draw text with word wrap
-        let line_height = 18.0;
-        let max_chars_per_line = 45;
-        let lines: Vec<&str> = text
-            .as_bytes()
-            .chunks(max_chars_per_line)
-            .map(|chunk| str::from_utf8(chunk).unwrap())
-            .collect();
-        for (i, line) in lines.iter().take(3).enumerate() {
-            draw_text(
-                line,
-                input_x + 5.0,
-                input_y + 16.0 + i as f32 * line_height,
-                16.0,
-                WHITE,
-            );
-        }
 
         // Hint
         draw_text(
-            "Enter to confirm, Esc to cancel",
+            "Enter to confirm, Shift+Enter for new line, Esc to cancel",
             dialog_x + 10.0,
-            dialog_y + 135.0,
+            dialog_y + 175.0,
             14.0,
             GRAY,
         );
@@ -740,24 +978,21 @@ impl Editor {
     }
 
     fn render_grid(&self, grid: &Grid, pane: usize, pane_width: f32, cell_size: f32, label: &str) {
-        let pane_x = TOOLBAR_WIDTH + PADDING + pane as f32 * (pane_width + PADDING);
+        let pane_x = self.panes_x() + pane as f32 * (pane_width + PADDING);
 
         // Pane background
         draw_rectangle(
             pane_x,
             PADDING,
             pane_width,
-            screen_height() - PADDING * 2.0,
+            self.pane_rect(pane).h,
             Color::from_rgba(40, 40, 50, 255),
         );
 
-        // Label
-        draw_text(label, pane_x + 4.0, PADDING + 20.0, 26.0, WHITE);
-
         let grid_w = grid.width() as f32 * cell_size;
         let grid_h = grid.height() as f32 * cell_size;
-        let offset_x = pane_x + (pane_width - grid_w) / 2.0;
-        let offset_y = PADDING + (screen_height() - PADDING * 2.0 - grid_h) / 2.0;
+        let (offset_x, offset_y) = self.grid_offset(pane);
+        set_clip(Some(self.pane_rect(pane)));
 
         // Grid lines
         for i in 0..=grid.width() {
@@ -771,6 +1006,9 @@ impl Editor {
 
         // Draw portals
         for (pos, level) in grid.portals() {
+            if !self.cell_visible(pos, pane) {
+                continue;
+            }
             let completed = self.game.is_level_completed(level);
             let texture = self.sprites.portal(completed);
             draw_texture_ex(
@@ -787,6 +1025,9 @@ impl Editor {
 
         // Draw notes
         for (pos, _) in grid.notes() {
+            if !self.cell_visible(pos, pane) {
+                continue;
+            }
             draw_texture_ex(
                 self.sprites.note(),
                 offset_x + pos.x as f32 * cell_size,
@@ -801,6 +1042,9 @@ impl Editor {
 
         // Draw cells
         for (pos, cell) in grid.entries() {
+            if matches!(cell, Cell::Empty) || !self.cell_visible(pos, pane) {
+                continue;
+            }
             let px = offset_x + pos.x as f32 * cell_size;
             let py = offset_y + pos.y as f32 * cell_size;
 
@@ -836,9 +1080,14 @@ impl Editor {
             }
         }
 
+        set_clip(None);
+
+        // Label
+        draw_text(label, pane_x + 4.0, PADDING + 20.0, 26.0, WHITE);
+
         // Show game state on right pane
         if pane == 1 {
-            let play_state = self.game.state.play_state();
+            let play_state = self.shown_play_state();
             let state_text = match play_state {
                 PlayState::Playing => "",
                 PlayState::GameOver => "GAME OVER",
@@ -849,7 +1098,7 @@ impl Editor {
                 draw_text(
                     state_text,
                     pane_x + (pane_width - dims.width) / 2.0,
-                    offset_y + grid_h + 30.0,
+                    (offset_y + grid_h + 30.0).min(PADDING + self.pane_rect(pane).h - 10.0),
                     32.0,
                     if play_state == PlayState::Won {
                         GREEN
@@ -861,20 +1110,68 @@ impl Editor {
         }
     }
 
-    fn toolbar_button_rect(index: usize) -> (f32, f32, f32, f32) {
-        let y = PADDING + index as f32 * 40.0;
+    /// Baseline of the move count, below the tool and paint mode buttons.
+    fn moves_y(&self) -> f32 {
+        PADDING + (Tool::all().len() + 2) as f32 * 40.0 + 20.0 - self.toolbar_scroll
+    }
+
+    /// Symmetry button, below the paint mode.
+    fn symmetry_button_rect(&self) -> Rect {
+        let (x, y, w, h) = self.toolbar_button_rect(Tool::all().len() + 1);
+        Rect { x, y, w, h }
+    }
+
+    /// Paint mode button, directly below the tools.
+    fn paint_mode_button_rect(&self) -> Rect {
+        let (x, y, w, h) = self.toolbar_button_rect(Tool::all().len());
+        Rect { x, y, w, h }
+    }
+
+    fn toolbar_button_rect(&self, index: usize) -> (f32, f32, f32, f32) {
+        let y = PADDING + index as f32 * 40.0 - self.toolbar_scroll;
         (PADDING, y, TOOLBAR_WIDTH - PADDING * 2.0, 35.0)
     }
 
+    /// How far the toolbar can scroll to show its last button on a short window.
+    fn max_toolbar_scroll(&self) -> f32 {
+        let wires = self.wiring_button_rect();
+        let bottom = wires.y + wires.h + PADDING + self.toolbar_scroll;
+        (bottom - screen_height()).max(0.0)
+    }
+
+    /// The mouse wheel scrolls the toolbar while the mouse is over it.
+    fn scroll_toolbar(&mut self) {
+        let (_, scroll_y) = mouse_wheel();
+        if mouse_over_toolbar() && scroll_y != 0.0 {
+            self.toolbar_scroll -= scroll_y.signum() * 40.0;
+        }
+        // Also keeps the scroll in range when the window grows
+        self.toolbar_scroll = self.toolbar_scroll.clamp(0.0, self.max_toolbar_scroll());
+    }
+
     fn click_toolbar(&mut self, mx: f32, my: f32) -> bool {
+        if self.click_stamp_palette(mx, my) {
+            return true;
+        }
+
         for (i, tool) in Tool::all().iter().enumerate() {
-            let (bx, by, bw, bh) = Self::toolbar_button_rect(i);
+            let (bx, by, bw, bh) = self.toolbar_button_rect(i);
             if mx >= bx && mx < bx + bw && my >= by && my < by + bh {
                 self.tool = *tool;
                 return true;
             }
         }
 
+        if self.paint_mode_button_rect().contains(mx, my) {
+            self.paint_mode = self.paint_mode.next();
+            return true;
+        }
+
+        if self.symmetry_button_rect().contains(mx, my) {
+            self.symmetry = self.symmetry.next();
+            return true;
+        }
+
         // Check size buttons
         let [w_minus, w_plus, h_minus, h_plus] = self.size_button_rects();
 
@@ -895,6 +1192,31 @@ impl Editor {
             return true;
         }
 
+        if self.info_button_rect().contains(mx, my) {
+            self.open_metadata_dialog();
+            return true;
+        }
+
+        if self.solve_button_rect().contains(mx, my) {
+            self.toggle_solver();
+            return true;
+        }
+
+        if self.debug_button_rect().contains(mx, my) {
+            self.toggle_debugger();
+            return true;
+        }
+
+        if self.heatmap_button_rect().contains(mx, my) {
+            self.toggle_heatmap();
+            return true;
+        }
+
+        if self.wiring_button_rect().contains(mx, my) {
+            self.toggle_wiring();
+            return true;
+        }
+
         false
     }
 
@@ -910,29 +1232,51 @@ impl Editor {
 
         // Tools
         for (i, tool) in Tool::all().iter().enumerate() {
-            let (bx, by, bw, bh) = Self::toolbar_button_rect(i);
-            let selected = self.tool == *tool;
-            let bg_color = if selected {
-                Color::from_rgba(80, 80, 100, 255)
-            } else {
-                Color::from_rgba(50, 50, 60, 255)
-            };
-
-            draw_rectangle(bx, by, bw, bh, bg_color);
-
+            let (x, y, w, h) = self.toolbar_button_rect(i);
             let label = format!("[{}] {}", tool.key(), tool.name());
-            let dims = measure_text(&label, None, 22, 1.0);
-            draw_text(
-                &label,
-                bx + (bw - dims.width) / 2.0,
-                by + (bh + dims.offset_y) / 2.0,
-                22.0,
-                WHITE,
-            );
+            toggle_button(Rect { x, y, w, h }, &label, self.tool == *tool);
         }
 
+        // Paint mode, used by tools that place cells
+        let mode = self.paint_mode_button_rect();
+        draw_rectangle(
+            mode.x,
+            mode.y,
+            mode.w,
+            mode.h,
+            Color::from_rgba(60, 50, 70, 255),
+        );
+        let label = format!("[{}] {}", self.paint_mode.key(), self.paint_mode.name());
+        let dims = measure_text(&label, None, 22, 1.0);
+        draw_text(
+            &label,
+            mode.x + (mode.w - dims.width) / 2.0,
+            mode.y + (mode.h + dims.offset_y) / 2.0,
+            22.0,
+            WHITE,
+        );
+
+        // Symmetry mode
+        let sym = self.symmetry_button_rect();
+        draw_rectangle(
+            sym.x,
+            sym.y,
+            sym.w,
+            sym.h,
+            Color::from_rgba(60, 50, 70, 255),
+        );
+        let label = format!("[y] Sym {}", self.symmetry.name());
+        let dims = measure_text(&label, None, 22, 1.0);
+        draw_text(
+            &label,
+            sym.x + (sym.w - dims.width) / 2.0,
+            sym.y + (sym.h + dims.offset_y) / 2.0,
+            22.0,
+            WHITE,
+        );
+
         // Move count below tools
-        let moves_y = PADDING + Tool::all().len() as f32 * 40.0 + 20.0;
+        let moves_y = self.moves_y();
         let moves_text = format!("Moves: {}", self.input_history.len());
         draw_text(&moves_text, PADDING, moves_y, 26.0, WHITE);
 
@@ -949,6 +1293,108 @@ impl Editor {
         let height_y = width_y + 30.0;
         let (h_minus, h_plus) = self.render_size_row("H", self.initial_grid.height(), height_y);
         let _ = (h_minus, h_plus); // Positions used in click handler
+
+        // Level info button with the current display name underneath
+        let info = self.info_button_rect();
+        toggle_button(info, "[F2] Info", false);
+        draw_text(
+            &self.level_name,
+            PADDING,
+            info.y + info.h + 20.0,
+            18.0,
+            LIGHTGRAY,
+        );
+
+        // Solver toggle
+        let solve = self.solve_button_rect();
+        toggle_button(solve, "[F5] Solve", self.solver_enabled);
+
+        // Saved reference solution status
+        let (solution_text, solution_color) = match &self.saved_solution {
+            None => ("No solution".to_string(), GRAY),
+            Some(solution) if self.saved_solution_wins => {
+                (format!("Solution: {}", solution.len()), GREEN)
+            }
+            Some(_) => ("Solution BROKEN".to_string(), RED),
+        };
+        draw_text(
+            &solution_text,
+            PADDING,
+            solve.y + solve.h + 20.0,
+            18.0,
+            solution_color,
+        );
+
+        // Resolution debugger toggle
+        let debug = self.debug_button_rect();
+        toggle_button(debug, "[F7] Debug", self.debug_mode);
+
+        // Cyborg distance overlay toggle
+        let heat = self.heatmap_button_rect();
+        toggle_button(heat, "[F8] Heat", self.heatmap_enabled);
+
+        // Trigger wiring and portal overlay toggle
+        let wires = self.wiring_button_rect();
+        toggle_button(wires, "[F9] Wires", self.wiring_enabled);
+
+        // Scroll bar when the window is too short for every button
+        let max_scroll = self.max_toolbar_scroll();
+        if max_scroll > 0.0 {
+            let content = screen_height() + max_scroll;
+            let bar_h = screen_height() * screen_height() / content;
+            let bar_y = self.toolbar_scroll / max_scroll * (screen_height() - bar_h);
+            draw_rectangle(
+                TOOLBAR_WIDTH - 4.0,
+                bar_y,
+                3.0,
+                bar_h,
+                Color::from_rgba(90, 90, 110, 255),
+            );
+        }
+    }
+
+    fn solve_button_rect(&self) -> Rect {
+        let info = self.info_button_rect();
+        Rect {
+            y: info.y + info.h + 30.0,
+            ..info
+        }
+    }
+
+    /// Debugger toggle, below the solver toggle and saved solution status.
+    fn debug_button_rect(&self) -> Rect {
+        let solve = self.solve_button_rect();
+        Rect {
+            y: solve.y + solve.h + 30.0,
+            ..solve
+        }
+    }
+
+    fn heatmap_button_rect(&self) -> Rect {
+        let debug = self.debug_button_rect();
+        Rect {
+            y: debug.y + debug.h + 10.0,
+            ..debug
+        }
+    }
+
+    fn wiring_button_rect(&self) -> Rect {
+        let heat = self.heatmap_button_rect();
+        Rect {
+            y: heat.y + heat.h + 10.0,
+            ..heat
+        }
+    }
+
+    fn info_button_rect(&self) -> Rect {
+        let moves_y = self.moves_y();
+        let height_y = moves_y + 40.0 + 25.0 + 30.0;
+        Rect {
+            x: PADDING,
+            y: height_y + 40.0,
+            w: TOOLBAR_WIDTH - PADDING * 2.0,
+            h: 30.0,
+        }
     }
 
     fn render_size_row(&self, label: &str, value: usize, y: f32) -> ((f32, f32), (f32, f32)) {
@@ -995,7 +1441,7 @@ impl Editor {
         let minus_x = PADDING;
         let plus_x = TOOLBAR_WIDTH - PADDING - btn_size;
 
-        let moves_y = PADDING + Tool::all().len() as f32 * 40.0 + 20.0;
+        let moves_y = self.moves_y();
         let size_y = moves_y + 40.0;
         let width_y = size_y + 25.0;
         let height_y = width_y + 30.0;
@@ -1028,103 +1474,165 @@ impl Editor {
         ]
     }
 
-    fn save(&self, csv_path: &str, json_path: &str, level_name: &str) {
-        // Create parent directories if they don't exist
-        if let Some(parent) = Path::new(csv_path).parent() {
-            let _ = create_dir_all(parent);
+    /// Save the level. If the current move history wins, it becomes the reference solution.
+    fn save(&mut self) {
+        if self.game.state.play_state() == PlayState::Won {
+            self.saved_solution = Some(self.input_history.clone());
+            self.saved_solution_wins = true;
         }
 
-        let csv = self.initial_grid.to_csv();
-        write(csv_path, csv).expect("Failed to save CSV");
+        let mut metadata = self.initial_grid.metadata(&self.level_name);
+        metadata.solution = self.saved_solution.as_deref().map(Action::format_moves);
+        match write_level(&self.level_path, &self.initial_grid, &metadata) {
+            Ok(()) => {
+                self.file_error = None;
+                self.saved_state = Some(self.recovery());
+                self.level_file_state = Some(self.recovery());
+                Recovery::delete(&self.level_path);
+            }
+            Err(e) => self.file_error = Some(format!("Save failed: {}", e)),
+        }
+        self.record_level_file_times();
+    }
 
-        let json = self.initial_grid.to_json(level_name);
-        write(json_path, json).expect("Failed to save JSON");
+    /// Create an empty level on disk for a new portal target, unless it already exists.
+    fn create_empty_level(&mut self, level: &str) {
+        if !Path::new(&level_csv_path(level)).exists() {
+            let grid = new_level_grid();
+            if let Err(e) = write_level(level, &grid, &grid.metadata(level)) {
+                self.file_error = Some(format!("Creating {} failed: {}", level, e));
+            }
+        }
     }
 
     fn resize(&mut self, delta_w: i32, delta_h: i32) {
         let new_w = (self.initial_grid.width() as i32 + delta_w).max(1) as usize;
         let new_h = (self.initial_grid.height() as i32 + delta_h).max(1) as usize;
         self.initial_grid.resize(new_w, new_h);
+        self.clamp_edit_cursor();
+        self.clamp_pan();
         self.replay_inputs();
     }
 }
 
 pub struct App {
     editor: Editor,
-    csv_path: String,
-    json_path: String,
-    level_name: String,
 }
 
 impl App {
     pub fn new(sprites: Sprites, level_name: &str) -> Self {
-        let csv_path = format!("levels/{}.csv", level_name);
-        let json_path = format!("levels/{}.json", level_name);
-
         // Load existing level or create empty grid
-        let (grid, display_name) = if let Ok(csv) = read_to_string(&csv_path) {
-            let json_str = read_to_string(&json_path).unwrap();
-            let metadata = LevelMetadata::parse(&json_str);
-            let name = metadata.name.clone();
-            (Grid::from_csv_and_metadata(&csv, &metadata), name)
+        let (grid, display_name, solution) = if Path::new(&level_csv_path(level_name)).exists() {
+            hot_reload::read_level(level_name).unwrap_or_else(|e| panic!("{}: {}", level_name, e))
         } else {
-            let mut grid = Grid::create_empty(10, 10);
-            *grid.at_mut(Position::new(5, 5)) = Cell::Player(Dir4::South);
-            (grid, level_name.to_string())
+            (new_level_grid(), level_name.to_string(), None)
         };
 
-        let editor = Editor::new(grid, sprites);
-
-        Self {
-            editor,
-            csv_path,
-            json_path,
-            level_name: display_name,
+        let mut editor = Editor::new(grid, level_name.to_string(), display_name, sprites);
+        editor.reload_stamps();
+        if let Some(solution) = solution {
+            editor.load_saved_solution(solution);
         }
+
+        // Offer to restore work a previous session didn't save
+        let saved_state = editor.recovery();
+        editor.recovery_dialog = Recovery::load(level_name).filter(|r| *r != saved_state);
+        editor.level_file_state = Some(editor.recovery());
+        editor.saved_state = Some(saved_state);
+
+        Self { editor }
     }
 
     /// Run one frame of the editor loop. Returns true to continue.
     pub fn tick(&mut self) -> bool {
-        // Handle portal dialog input first (blocks other input)
-        if let Some((pos, ref mut text)) = self.editor.portal_dialog {
-            if is_key_pressed(KeyCode::Escape) {
-                self.editor.portal_dialog = None;
-            } else if is_key_pressed(KeyCode::Enter) && !text.is_empty() {
-                let level = text.clone();
-                self.editor.portal_dialog = None;
-                self.editor.place_portal(pos, level);
-            } else if is_key_pressed(KeyCode::Backspace) && !text.is_empty() {
-                text.pop();
-            } else if let Some(c) = get_char_pressed()
-                && (c.is_alphanumeric() || c == '_' || c == '-' || c == ' ' || c == '/')
-            {
-                text.push(c);
-            }
+        // Keep searching in the background of every frame, including while dialogs are open
+        self.editor.update_solver();
+        self.editor.update_heatmap();
+        self.editor.update_wiring();
+
+        // Handle recovery offer first (blocks other input)
+        if self.editor.recovery_dialog.is_some() {
+            self.editor.handle_recovery_dialog_input();
+            self.editor.render();
+            return true;
+        }
 
+        // Handle portal dialog input (blocks other input)
+        if self.editor.portal_dialog.is_some() {
+            self.editor.handle_portal_dialog_input();
             self.editor.render();
             return true;
         }
 
         // Handle note dialog input (blocks other input)
-        if let Some((pos, ref mut text)) = self.editor.note_dialog {
-            if is_key_pressed(KeyCode::Escape) {
-                self.editor.note_dialog = None;
-            } else if is_key_pressed(KeyCode::Enter) && !text.is_empty() {
-                let note_text = text.clone();
-                self.editor.note_dialog = None;
-                self.editor.place_note(pos, note_text);
-            } else if is_key_pressed(KeyCode::Backspace) && !text.is_empty() {
-                text.pop();
-            } else if let Some(c) = get_char_pressed()
-                && (c.is_alphanumeric() || c.is_ascii_punctuation() || c == ' ')
+        if let Some((pos, ref mut input)) = self.editor.note_dialog {
+            match input
+                .handle_input(|c| c.is_alphanumeric() || c.is_ascii_punctuation() || c == ' ')
             {
-                text.push(c);
+                TextInputEvent::Cancel => self.editor.note_dialog = None,
+                TextInputEvent::Submit if !input.text().is_empty() => {
+                    let note_text = input.text().to_string();
+                    self.editor.note_dialog = None;
+                    self.editor.place_note(pos, note_text);
+                }
+                TextInputEvent::Submit | TextInputEvent::None => {}
             }
 
             self.editor.render();
             return true;
         }
 
+        // Handle stamp name entry (blocks other input)
+        if self.editor.stamp_dialog.is_some() {
+            self.editor.handle_stamp_dialog_input();
+            self.editor.render();
+            return true;
+        }
+
+        // Handle metadata panel input (blocks other input)
+        if self.editor.metadata_dialog.is_some() {
+            self.editor.handle_metadata_dialog_input();
+            self.editor.render();
+            return true;
+        }
+
+        // Pick up changes to the level files made outside this editor
+        self.editor.check_level_files();
+        self.editor.autosave();
+
+        // Open metadata panel (F2)
+        if is_key_pressed(KeyCode::F2) {
+            self.editor.open_metadata_dialog();
+        }
+
+        // Toggle solver (F5) and load its next solution into the move history (F6)
+        if is_key_pressed(KeyCode::F5) {
+            self.editor.toggle_solver();
+        }
+        if is_key_pressed(KeyCode::F6) {
+            self.editor.load_next_solution();
+        }
+
+        // Toggle resolution debugger (F7)
+        if is_key_pressed(KeyCode::F7) {
+            self.editor.toggle_debugger();
+        }
+
+        // Toggle cyborg distance overlay (F8)
+        if is_key_pressed(KeyCode::F8) {
+            self.editor.toggle_heatmap();
+        }
+
+        // Toggle trigger wiring and portal overlay (F9)
+        if is_key_pressed(KeyCode::F9) {
+            self.editor.toggle_wiring();
+        }
+
+        // Toggle keyboard editing at a grid cursor (Tab)
+        if is_key_pressed(KeyCode::Tab) {
+            self.editor.toggle_edit_cursor();
+        }
+
         // Tool selection via character input
         if let Some(c) = get_char_pressed() {
             match c.to_ascii_lowercase() {
@@ -1140,6 +1648,22 @@ impl App {
                 'o' => self.editor.tool = Tool::BlackHole,
                 'x' => self.editor.tool = Tool::Explosive,
                 't' => self.editor.tool = Tool::Trigger,
+                'l' => self.editor.tool = Tool::Stamp,
+                'h' => self.editor.mirror_stamp(),
+                '0' => self.editor.reset_view(),
+                'y' => self.editor.symmetry = self.editor.symmetry.next(),
+                '[' => self.editor.step_timeline(false),
+                ']' => self.editor.step_timeline(true),
+                'b' => self.editor.paint_mode = PaintMode::Brush,
+                'f' => self.editor.paint_mode = PaintMode::Fill,
+                'k' => {
+                    // Toggle between hollow and filled rectangles
+                    self.editor.paint_mode = if self.editor.paint_mode == PaintMode::Rect {
+                        PaintMode::FilledRect
+                    } else {
+                        PaintMode::Rect
+                    };
+                }
                 'q' => {
                     // Q-pick: sample the cell under the cursor
                     let (mx, my) = mouse_position();
@@ -1151,9 +1675,10 @@ impl App {
             }
         }
 
-        // Scroll wheel changes trigger digit when Trigger tool is selected
-        let (_, scroll_y) = mouse_wheel();
-        if self.editor.tool == Tool::Trigger && scroll_y != 0.0 {
+        // Scroll wheel changes trigger digit when Trigger tool is selected (Ctrl+wheel zooms)
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let scroll_y = grid_wheel();
+        if self.editor.tool == Tool::Trigger && scroll_y != 0.0 && !ctrl {
             if scroll_y > 0.0 {
                 self.editor.trigger_digit = if self.editor.trigger_digit >= 9 {
                     1
@@ -1169,8 +1694,16 @@ impl App {
             }
         }
 
-        // Resize grid (Shift+Arrow)
-        if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+        // Scroll wheel rotates the stamp when Stamp tool is selected
+        if self.editor.tool == Tool::Stamp && scroll_y != 0.0 && !ctrl {
+            self.editor.rotate_stamp(scroll_y < 0.0);
+        }
+
+        if self.editor.edit_cursor.is_some() {
+            // Arrows move the edit cursor, Shift+arrows select, Enter places and Delete erases
+            self.editor.handle_edit_cursor_input();
+        } else if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+            // Resize grid (Shift+Arrow)
             if is_key_pressed(KeyCode::Up) {
                 self.editor.resize(0, -1);
             }
@@ -1183,51 +1716,58 @@ impl App {
             if is_key_pressed(KeyCode::Right) {
                 self.editor.resize(1, 0);
             }
+        } else if self.editor.debug_mode {
+            // Movement input traces the move in the debugger
+            self.editor.handle_debugger_input();
         } else {
             // Movement input (add to history)
             if is_key_pressed(KeyCode::Up) {
-                self.editor.add_input(Action::Move(Dir4::North));
+                self.editor.insert_input(Action::Move(Dir4::North));
             }
             if is_key_pressed(KeyCode::Down) {
-                self.editor.add_input(Action::Move(Dir4::South));
+                self.editor.insert_input(Action::Move(Dir4::South));
             }
             if is_key_pressed(KeyCode::Left) {
-                self.editor.add_input(Action::Move(Dir4::West));
+                self.editor.insert_input(Action::Move(Dir4::West));
             }
             if is_key_pressed(KeyCode::Right) {
-                self.editor.add_input(Action::Move(Dir4::East));
+                self.editor.insert_input(Action::Move(Dir4::East));
             }
             if is_key_pressed(KeyCode::Space) {
-                self.editor.add_input(Action::Stall);
+                self.editor.insert_input(Action::Stall);
             }
         }
 
         // Undo last move (u or backspace)
         if is_key_pressed(KeyCode::U) || is_key_pressed(KeyCode::Backspace) {
-            self.editor.remove_last_input();
+            self.editor.remove_input_before_cursor();
         }
 
-        // Escape: cancel drag/selection, or clear moves
+        // Escape: cancel shape/drag/selection, or clear moves
         if is_key_pressed(KeyCode::Escape) {
-            if self.editor.dragging.is_some() || self.editor.dragging_selection.is_some() {
+            if self.editor.shape_drag.is_some() {
+                self.editor.shape_drag = None;
+            } else if self.editor.debugger.is_some() {
+                self.editor.debugger = None;
+            } else if self.editor.dragging.is_some() || self.editor.dragging_selection.is_some() {
                 self.editor.cancel_drag();
             } else if !self.editor.selection.is_empty() || self.editor.selecting_rect.is_some() {
                 self.editor.clear_selection();
             } else {
                 self.editor.input_history.clear();
+                self.editor.timeline_cursor = None;
                 self.editor.replay_inputs();
             }
         }
 
         // Save
         if is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::S) {
-            self.editor
-                .save(&self.csv_path, &self.json_path, &self.level_name);
+            self.editor.save();
         }
 
         // Scroll wheel to rotate player direction (only when Player tool selected)
-        if self.editor.tool == Tool::Player {
-            let (_, scroll_y) = mouse_wheel();
+        if self.editor.tool == Tool::Player && !ctrl {
+            let scroll_y = grid_wheel();
             if scroll_y < 0.0 {
                 self.editor.player_dir = self.editor.player_dir.rotate_cw();
             } else if scroll_y > 0.0 {
@@ -1235,6 +1775,18 @@ impl App {
             }
         }
 
+        // Truncate moves after the timeline cursor
+        if self.editor.edit_cursor.is_none() && is_key_pressed(KeyCode::Delete) {
+            self.editor.truncate_at_cursor();
+        }
+
+        // Zoom, pan and minimap navigation, then timeline scrubbing
+        self.editor.scroll_toolbar();
+        if self.editor.handle_view_input() || self.editor.handle_timeline_mouse() {
+            self.editor.render();
+            return true;
+        }
+
         // Mouse handling
         let (mx, my) = mouse_position();
 
@@ -1268,33 +1820,60 @@ impl App {
                         }
                         Tool::Portal => {
                             // Open portal dialog
-                            self.editor.portal_dialog = Some((pos, String::new()));
+                            self.editor.open_portal_dialog(pos);
                         }
                         Tool::Note => {
                             // Open note dialog
-                            self.editor.note_dialog = Some((pos, String::new()));
+                            self.editor.open_note_dialog(pos);
                         }
-                        tool => {
-                            // Place cell and start tracking drag-painting
-                            if let Some(cell) =
-                                tool.to_cell(pos, self.editor.player_dir, self.editor.trigger_digit)
-                            {
-                                self.editor.place_cell(pos, cell);
-                                self.editor.last_paint_pos = Some(pos);
-                            }
+                        Tool::Stamp => {
+                            // Stamp the selected stamp with its corner here
+                            self.editor.place_stamp(pos);
                         }
+                        _ if is_key_down(KeyCode::LeftShift)
+                            || is_key_down(KeyCode::RightShift) =>
+                        {
+                            // Shift-drag draws a straight line
+                            self.editor.start_shape(Shape::Line, pos);
+                        }
+                        tool => match self.editor.paint_mode {
+                            PaintMode::Brush => {
+                                // Place cell and start tracking drag-painting
+                                if let Some(cell) = tool.to_cell(
+                                    pos,
+                                    self.editor.player_dir,
+                                    self.editor.trigger_digit,
+                                ) {
+                                    self.editor.place_cell(pos, cell);
+                                    self.editor.last_paint_pos = Some(pos);
+                                }
+                            }
+                            PaintMode::Fill => self.editor.fill(pos),
+                            PaintMode::Rect => self.editor.start_shape(Shape::Rect, pos),
+                            PaintMode::FilledRect => {
+                                self.editor.start_shape(Shape::FilledRect, pos)
+                            }
+                        },
                     }
                 } else {
                     // Clicked outside grid: clear selection
                     self.editor.clear_selection();
                 }
             } else {
-                // Mouse held down - update selection rectangle or continue drag-painting
+                // Mouse held down - update selection rectangle or shape, or continue drag-painting
                 if self.editor.selecting_rect.is_some() {
                     if let Some((pos, _)) = self.editor.screen_to_grid(mx, my) {
                         self.editor.update_selection(pos);
                     }
-                } else if !matches!(self.editor.tool, Tool::Move | Tool::Portal | Tool::Note)
+                } else if self.editor.shape_drag.is_some() {
+                    if let Some((pos, _)) = self.editor.screen_to_grid(mx, my) {
+                        self.editor.update_shape(pos);
+                    }
+                } else if self.editor.paint_mode == PaintMode::Brush
+                    && !matches!(
+                        self.editor.tool,
+                        Tool::Move | Tool::Portal | Tool::Note | Tool::Stamp
+                    )
                     && self.editor.dragging.is_none()
                     && let Some((pos, _)) = self.editor.screen_to_grid(mx, my)
                     && self.editor.last_paint_pos != Some(pos)
@@ -1309,11 +1888,12 @@ impl App {
                     self.editor.last_paint_pos = Some(pos);
                 }
             }
-        } else {
+        } else if !self.editor.keyboard_drag {
             // No mouse button down - reset paint tracking and handle drag/selection release
             if self.editor.selecting_rect.is_some() {
                 self.editor.end_selection();
             }
+            self.editor.end_shape();
             if self.editor.dragging.is_some() || self.editor.dragging_selection.is_some() {
                 if let Some((pos, _)) = self.editor.screen_to_grid(mx, my) {
                     self.editor.end_drag(pos);
@@ -1329,3 +1909,25 @@ impl App {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dragged_portals_keep_their_requirements() {
+        let mut grid = Grid::create_empty(3, 1);
+        let (from, to) = (Position::new(0, 0), Position::new(2, 0));
+        grid.insert_portal(from, "target".to_string());
+        grid.set_portal_requires(from, vec!["key".to_string()]);
+        let saved = grid.metadata("level").to_json();
+
+        let item = DraggedItem::take(&mut grid, from, PositionDelta::new(0, 0)).unwrap();
+        item.put_down(&mut grid, to);
+        assert_eq!(grid.portal_requires(to), ["key"]);
+
+        let item = DraggedItem::take(&mut grid, to, PositionDelta::new(0, 0)).unwrap();
+        item.put_down(&mut grid, from);
+        assert_eq!(grid.metadata("level").to_json(), saved);
+    }
+}
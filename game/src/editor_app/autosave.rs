@@ -0,0 +1,190 @@
+use std::fs::{create_dir_all, read_to_string, remove_file, write};
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::Action;
+use crate::grid::{Grid, LevelMetadata};
+
+use super::Editor;
+
+/// Seconds between autosaves of unsaved work.
+const AUTOSAVE_INTERVAL: f64 = 30.0;
+
+/// Unsaved editor state for one level, written periodically so a crash doesn't lose it.
+#[derive(Serialize, Deserialize, PartialEq)]
+pub(super) struct Recovery {
+    csv: String,
+    metadata: LevelMetadata,
+    /// Move history in move notation (see `Action::format_moves`)
+    moves: String,
+}
+
+/// Recovery file for a level in the user data directory, or None where there isn't one.
+fn recovery_path(level: &str) -> Option<PathBuf> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let dirs = directories::ProjectDirs::from("", "dspyz", "InfestationGame")?;
+        Some(
+            dirs.data_dir()
+                .join("editor_recovery")
+                .join(format!("{}.json", level)),
+        )
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = level;
+        None
+    }
+}
+
+impl Recovery {
+    /// Whether both have the same level, whatever moves were played in it.
+    pub(super) fn same_level(&self, other: &Recovery) -> bool {
+        self.csv == other.csv && self.metadata == other.metadata
+    }
+
+    /// Load the recovery file for a level, if a previous session left one behind.
+    pub(super) fn load(level: &str) -> Option<Self> {
+        let path = recovery_path(level)?;
+        let json = read_to_string(&path).ok()?;
+        serde_json::from_str(&json)
+            .map_err(|e| log::warn!("Failed to parse {}: {}", path.display(), e))
+            .ok()
+    }
+
+    fn write(&self, level: &str) -> Result<(), String> {
+        let Some(path) = recovery_path(level) else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        write(&path, json).map_err(|e| e.to_string())
+    }
+
+    pub(super) fn delete(level: &str) {
+        if let Some(path) = recovery_path(level) {
+            let _ = remove_file(path);
+        }
+    }
+}
+
+impl Editor {
+    /// The level and move history as they are now.
+    pub(super) fn recovery(&self) -> Recovery {
+        let mut metadata = self.initial_grid.metadata(&self.level_name);
+        metadata.solution = self.saved_solution.as_deref().map(Action::format_moves);
+        Recovery {
+            csv: self.initial_grid.to_csv(),
+            metadata,
+            moves: Action::format_moves(&self.input_history),
+        }
+    }
+
+    /// Write the recovery file if anything changed since the last save or autosave.
+    pub(super) fn autosave(&mut self) {
+        let now = get_time();
+        if now - self.last_autosave < AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_autosave = now;
+
+        let recovery = self.recovery();
+        if self.saved_state.as_ref() == Some(&recovery) {
+            return;
+        }
+        match recovery.write(&self.level_path) {
+            Ok(()) => self.saved_state = Some(recovery),
+            Err(e) => self.file_error = Some(format!("Autosave failed: {}", e)),
+        }
+    }
+
+    /// Replace the level and move history with a recovered session.
+    fn restore(&mut self, recovery: Recovery) -> Result<(), String> {
+        self.initial_grid = Grid::try_from_csv_and_metadata(&recovery.csv, &recovery.metadata)?;
+        self.level_name = recovery.metadata.name;
+        self.saved_solution = recovery
+            .metadata
+            .solution
+            .as_deref()
+            .and_then(Action::parse_moves);
+        self.input_history = Action::parse_moves(&recovery.moves).unwrap_or_default();
+        self.timeline_cursor = None;
+        self.clamp_edit_cursor();
+        self.clamp_pan();
+        self.replay_inputs();
+        Ok(())
+    }
+
+    /// Enter restores the recovered session, Escape discards it.
+    pub(super) fn handle_recovery_dialog_input(&mut self) {
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            let Some(recovery) = self.recovery_dialog.take() else {
+                return;
+            };
+            // Keep the file until the restored work is saved
+            self.saved_state = Some(self.recovery());
+            if let Err(e) = self.restore(recovery) {
+                self.file_error = Some(format!("Restore failed: {}", e));
+            }
+        } else if is_key_pressed(KeyCode::Escape) {
+            self.recovery_dialog = None;
+            Recovery::delete(&self.level_path);
+        }
+    }
+
+    pub(super) fn render_recovery_dialog(&self) {
+        if self.recovery_dialog.is_none() {
+            return;
+        }
+
+        let dialog_w = 460.0;
+        let dialog_h = 130.0;
+        let dialog_x = (screen_width() - dialog_w) / 2.0;
+        let dialog_y = (screen_height() - dialog_h) / 2.0;
+
+        // Dim background
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::from_rgba(0, 0, 0, 150),
+        );
+
+        // Dialog box
+        draw_rectangle(
+            dialog_x,
+            dialog_y,
+            dialog_w,
+            dialog_h,
+            Color::from_rgba(40, 40, 50, 255),
+        );
+        draw_rectangle_lines(dialog_x, dialog_y, dialog_w, dialog_h, 2.0, WHITE);
+
+        draw_text(
+            "Recover unsaved changes?",
+            dialog_x + 10.0,
+            dialog_y + 30.0,
+            26.0,
+            WHITE,
+        );
+        draw_text(
+            "A previous session of this level ended without saving.",
+            dialog_x + 10.0,
+            dialog_y + 60.0,
+            18.0,
+            LIGHTGRAY,
+        );
+        draw_text(
+            "Enter to restore, Esc to discard",
+            dialog_x + 10.0,
+            dialog_y + dialog_h - 15.0,
+            16.0,
+            GRAY,
+        );
+    }
+}
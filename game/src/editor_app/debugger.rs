@@ -0,0 +1,241 @@
+use macroquad::prelude::*;
+
+use crate::direction::Dir4;
+use crate::game::{Action, Phase, PlayState, Rejection, TraceStep, trace_action};
+use crate::grid::{Cell, Grid};
+use crate::position::Position;
+
+use super::Editor;
+
+/// One action resolved phase by phase, with the phase currently shown.
+pub(super) struct Debugger {
+    action: Action,
+    steps: Vec<TraceStep>,
+    index: usize,
+}
+
+impl Debugger {
+    fn step(&self) -> Option<&TraceStep> {
+        self.steps.get(self.index)
+    }
+
+    /// Grid to show in the right pane for the current phase.
+    pub(super) fn grid(&self) -> Option<&Grid> {
+        self.step().map(|step| &step.grid)
+    }
+
+    pub(super) fn label(&self) -> String {
+        format!(
+            "Debug {}: {}/{}",
+            self.action.notation(),
+            self.index + 1,
+            self.steps.len()
+        )
+    }
+}
+
+fn format_pos(pos: Position) -> String {
+    format!("({}, {})", pos.x, pos.y)
+}
+
+fn describe_phase(phase: Phase) -> String {
+    match phase {
+        Phase::PlayerMove(Action::Move(dir)) => format!("Player moves {:?}", dir),
+        Phase::PlayerMove(Action::Stall) => "Player stalls".to_string(),
+        Phase::CyborgRat {
+            pos,
+            distance: Some(distance),
+        } => format!("Cyborg rat at {}, distance {}", format_pos(pos), distance),
+        Phase::CyborgRat {
+            pos,
+            distance: None,
+        } => format!("Cyborg rat at {}, no path to player", format_pos(pos)),
+        Phase::Rat { pos, dist_sq } => {
+            format!("Rat at {}, squared distance {}", format_pos(pos), dist_sq)
+        }
+        Phase::FinishMoving => "Moves land".to_string(),
+        Phase::StartZapWave => "Triggers turn to walls".to_string(),
+        Phase::FinishZapWave => "Zap walls spread".to_string(),
+        Phase::StartExplosionWave => "Explosives go off".to_string(),
+        Phase::FinishExplosionWave => "Explosions destroy neighbors".to_string(),
+    }
+}
+
+fn describe_rejection(rejection: Rejection) -> String {
+    match rejection {
+        Rejection::Blocked(cell) => format!("blocked by {:?}", cell),
+        Rejection::Sword => "sword".to_string(),
+        Rejection::NoPath => "no path".to_string(),
+        Rejection::NotCloser => "not closer".to_string(),
+        Rejection::NextToPlayer => "next to player".to_string(),
+        Rejection::NotBest => "not the closest".to_string(),
+    }
+}
+
+/// Position of the rat or cyborg rat that moves in a phase.
+fn mover(phase: Phase) -> Option<Position> {
+    match phase {
+        Phase::CyborgRat { pos, .. } | Phase::Rat { pos, .. } => Some(pos),
+        _ => None,
+    }
+}
+
+impl Editor {
+    /// Turn debugger mode on or off. While on, move keys trace a move instead of playing it.
+    pub(super) fn toggle_debugger(&mut self) {
+        self.debug_mode = !self.debug_mode;
+        self.debugger = None;
+    }
+
+    /// Resolve `action` from the current game state and show its first phase.
+    pub(super) fn debug_action(&mut self, action: Action) {
+        if self.shown_play_state() != PlayState::Playing
+            || !self
+                .shown_grid()
+                .entries()
+                .any(|(_, cell)| matches!(cell, Cell::Player(_)))
+        {
+            return;
+        }
+        self.debugger = Some(Debugger {
+            action,
+            steps: trace_action(self.shown_grid(), action),
+            index: 0,
+        });
+    }
+
+    pub(super) fn handle_debugger_input(&mut self) {
+        if is_key_pressed(KeyCode::Up) {
+            self.debug_action(Action::Move(Dir4::North));
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.debug_action(Action::Move(Dir4::South));
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.debug_action(Action::Move(Dir4::West));
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.debug_action(Action::Move(Dir4::East));
+        }
+        if is_key_pressed(KeyCode::Space) {
+            self.debug_action(Action::Stall);
+        }
+
+        let Some(debugger) = &mut self.debugger else {
+            return;
+        };
+        let last = debugger.steps.len().saturating_sub(1);
+        if is_key_pressed(KeyCode::PageDown) {
+            debugger.index = (debugger.index + 1).min(last);
+        }
+        if is_key_pressed(KeyCode::PageUp) {
+            debugger.index = debugger.index.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Home) {
+            debugger.index = 0;
+        }
+        if is_key_pressed(KeyCode::End) {
+            debugger.index = last;
+        }
+
+        // Enter keeps the traced move in the history
+        if is_key_pressed(KeyCode::Enter) {
+            let action = debugger.action;
+            self.debugger = None;
+            self.insert_input(action);
+        }
+    }
+
+    /// Highlights on the right pane and a description of the current phase below it.
+    pub(super) fn render_debugger(&self, cell_size: f32) {
+        if !self.debug_mode {
+            return;
+        }
+
+        let (pane_width, _, _) = self.pane_layout();
+        let pane_x = self.pane_rect(1).x;
+        let mut lines: Vec<(String, Color)> = Vec::new();
+
+        match &self.debugger {
+            None => lines.push((
+                "Debugger: press an arrow or Space to trace a move".to_string(),
+                YELLOW,
+            )),
+            Some(debugger) => {
+                let step = debugger.step();
+
+                // Processing order of every rat and cyborg rat, numbered on the grid
+                let movers: Vec<Position> = debugger
+                    .steps
+                    .iter()
+                    .filter_map(|s| mover(s.phase))
+                    .collect();
+                for (i, &pos) in movers.iter().enumerate() {
+                    let (x, y) = self.grid_to_screen(pos, 1);
+                    draw_text(&(i + 1).to_string(), x + 2.0, y + 14.0, 16.0, YELLOW);
+                }
+
+                if let Some(step) = step {
+                    lines.push((describe_phase(step.phase), WHITE));
+
+                    if let Some(pos) = mover(step.phase) {
+                        let (x, y) = self.grid_to_screen(pos, 1);
+                        draw_rectangle_lines(x, y, cell_size, cell_size, 3.0, YELLOW);
+
+                        for &(dir, rejection) in &step.rejected {
+                            let (tx, ty) = self.grid_to_screen(pos + dir.delta(), 1);
+                            let color = Color::from_rgba(255, 80, 80, 220);
+                            draw_line(tx, ty, tx + cell_size, ty + cell_size, 2.0, color);
+                            draw_line(tx + cell_size, ty, tx, ty + cell_size, 2.0, color);
+                            lines.push((
+                                format!("  {:?}: {}", dir, describe_rejection(rejection)),
+                                Color::from_rgba(255, 120, 120, 255),
+                            ));
+                        }
+
+                        match step.chosen {
+                            Some(dir) => {
+                                let (tx, ty) = self.grid_to_screen(pos + dir.delta(), 1);
+                                draw_rectangle_lines(tx, ty, cell_size, cell_size, 3.0, GREEN);
+                                lines.push((format!("  Moves {:?}", dir), GREEN));
+                            }
+                            None => lines.push(("  Stays and faces the player".to_string(), GRAY)),
+                        }
+
+                        let order = movers.iter().position(|&p| p == pos).unwrap_or(0);
+                        lines.push((
+                            format!("  Order {} of {}", order + 1, movers.len()),
+                            LIGHTGRAY,
+                        ));
+                    }
+                }
+                lines.push((
+                    "PgUp/PgDn step, Home/End, Enter apply, F7 exit".to_string(),
+                    GRAY,
+                ));
+            }
+        }
+
+        // Text panel along the bottom of the right pane
+        let line_height = 18.0;
+        let panel_h = lines.len() as f32 * line_height + 8.0;
+        let pane = self.pane_rect(1);
+        let panel_y = pane.y + pane.h - panel_h;
+        draw_rectangle(
+            pane_x,
+            panel_y,
+            pane_width,
+            panel_h,
+            Color::from_rgba(20, 20, 30, 220),
+        );
+        for (i, (text, color)) in lines.iter().enumerate() {
+            draw_text(
+                text,
+                pane_x + 6.0,
+                panel_y + 4.0 + (i + 1) as f32 * line_height - 4.0,
+                16.0,
+                *color,
+            );
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use macroquad::prelude::*;
+
+use crate::position::{Position, PositionDelta};
+
+use super::paint::{PaintMode, Shape};
+use super::{Editor, Tool};
+
+impl Editor {
+    /// Tab: switch between recording moves with the arrow keys and editing at a grid cursor.
+    pub(super) fn toggle_edit_cursor(&mut self) {
+        self.edit_cursor = match self.edit_cursor {
+            Some(_) => None,
+            None => {
+                // Start where the mouse is, or in the middle of the grid
+                let (mx, my) = mouse_position();
+                Some(self.screen_to_grid(mx, my).map_or_else(
+                    || {
+                        Position::new(
+                            self.initial_grid.width() / 2,
+                            self.initial_grid.height() / 2,
+                        )
+                    },
+                    |(pos, _)| pos,
+                ))
+            }
+        };
+        self.cursor_anchor = None;
+        if self.keyboard_drag {
+            self.shape_drag = None;
+            self.cancel_drag();
+            self.keyboard_drag = false;
+        }
+    }
+
+    /// Keep the cursor and selection anchor on the grid after it shrinks.
+    pub(super) fn clamp_edit_cursor(&mut self) {
+        let bounds = self.initial_grid.bounds();
+        self.edit_cursor = self.edit_cursor.map(|pos| pos.clamped(bounds));
+        self.cursor_anchor = self.cursor_anchor.map(|pos| pos.clamped(bounds));
+    }
+
+    /// Arrows move the cursor, Shift+arrows extend a selection from where it started,
+    /// Enter applies the current tool and Delete erases.
+    pub(super) fn handle_edit_cursor_input(&mut self) {
+        let Some(cursor) = self.edit_cursor else {
+            return;
+        };
+        // A drag or shape ended by Escape or the mouse is no longer the keyboard's
+        if self.shape_drag.is_none() && self.dragging.is_none() && self.dragging_selection.is_none()
+        {
+            self.keyboard_drag = false;
+        }
+
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        let step = [
+            (KeyCode::Up, PositionDelta::new(0, -1)),
+            (KeyCode::Down, PositionDelta::new(0, 1)),
+            (KeyCode::Left, PositionDelta::new(-1, 0)),
+            (KeyCode::Right, PositionDelta::new(1, 0)),
+        ]
+        .into_iter()
+        .filter(|&(key, _)| is_key_pressed(key))
+        .map(|(_, delta)| delta)
+        .next();
+
+        if let Some(delta) = step {
+            let moved = cursor + delta;
+            let bounds = self.initial_grid.bounds();
+            let moved = if moved.in_bounds(bounds) {
+                moved
+            } else {
+                cursor
+            };
+            self.move_edit_cursor(moved, shift);
+            return;
+        }
+        // Enter and Delete edit the cell under the cursor, which must exist
+        if !cursor.in_bounds(self.initial_grid.bounds()) {
+            return;
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            self.apply_tool_at_cursor(cursor);
+        } else if is_key_pressed(KeyCode::Delete) {
+            self.erase_at_cursor(cursor);
+        }
+    }
+
+    fn move_edit_cursor(&mut self, pos: Position, extend_selection: bool) {
+        if extend_selection {
+            let anchor = *self
+                .cursor_anchor
+                .get_or_insert(self.edit_cursor.unwrap_or(pos));
+            self.selecting_rect = Some((anchor, pos));
+            self.end_selection();
+        } else {
+            self.cursor_anchor = None;
+        }
+        self.edit_cursor = Some(pos);
+        self.update_shape(pos);
+        self.scroll_to_cursor();
+    }
+
+    /// Pan just enough to show the cell under the cursor.
+    fn scroll_to_cursor(&mut self) {
+        let Some(cursor) = self.edit_cursor else {
+            return;
+        };
+        let (_, _, cell_size) = self.pane_layout();
+        let (x, y) = self.grid_to_screen(cursor, 0);
+        let rect = self.pane_rect(0);
+        if x < rect.x {
+            self.pan.x += rect.x - x;
+        } else if x + cell_size > rect.x + rect.w {
+            self.pan.x -= x + cell_size - (rect.x + rect.w);
+        }
+        if y < rect.y {
+            self.pan.y += rect.y - y;
+        } else if y + cell_size > rect.y + rect.h {
+            self.pan.y -= y + cell_size - (rect.y + rect.h);
+        }
+        self.clamp_pan();
+    }
+
+    /// Enter: what a left click at the cursor would do. Dragging and shapes finish on a second Enter.
+    fn apply_tool_at_cursor(&mut self, pos: Position) {
+        if self.keyboard_drag {
+            if self.shape_drag.is_some() {
+                self.end_shape();
+            } else {
+                self.end_drag(pos);
+            }
+            self.keyboard_drag = false;
+            return;
+        }
+
+        match self.tool {
+            Tool::Move => {
+                // Pick up the selection or the cell under the cursor, to drop with the next Enter
+                self.start_drag(pos);
+                self.keyboard_drag = self.dragging.is_some() || self.dragging_selection.is_some();
+            }
+            Tool::Portal => self.open_portal_dialog(pos),
+            Tool::Note => self.open_note_dialog(pos),
+            Tool::Stamp => self.place_stamp(pos),
+            tool => match self.paint_mode {
+                PaintMode::Brush => {
+                    if let Some(cell) = tool.to_cell(pos, self.player_dir, self.trigger_digit) {
+                        self.place_cell(pos, cell);
+                    }
+                }
+                PaintMode::Fill => self.fill(pos),
+                PaintMode::Rect | PaintMode::FilledRect => {
+                    let shape = if self.paint_mode == PaintMode::Rect {
+                        Shape::Rect
+                    } else {
+                        Shape::FilledRect
+                    };
+                    self.start_shape(shape, pos);
+                    self.keyboard_drag = true;
+                }
+            },
+        }
+    }
+
+    /// Delete: erase the selection, or the cell under the cursor.
+    fn erase_at_cursor(&mut self, pos: Position) {
+        if self.selection.is_empty() {
+            self.erase_cell(pos);
+            return;
+        }
+        let mut selection: Vec<Position> = self.selection.drain().collect();
+        selection.sort_by_key(|p| (p.y, p.x));
+        for pos in selection {
+            self.erase_cell(pos);
+        }
+    }
+
+    /// Cursor outline on both panes, with what Enter would place or drop.
+    pub(super) fn render_edit_cursor(&self, cell_size: f32) {
+        let Some(cursor) = self.edit_cursor else {
+            return;
+        };
+
+        // Dragged cells follow the cursor
+        let ghosts: Vec<_> = if let Some((_, items)) = &self.dragging_selection {
+            items
+                .iter()
+                .map(|item| (cursor + item.delta, item.cell))
+                .collect()
+        } else if let Some((_, cell)) = self.dragging {
+            vec![(cursor, cell)]
+        } else if self.shape_drag.is_none() {
+            self.tool
+                .to_cell(cursor, self.player_dir, self.trigger_digit)
+                .map(|cell| (cursor, cell))
+                .into_iter()
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let bounds = (self.initial_grid.width(), self.initial_grid.height());
+        for (pos, cell) in ghosts {
+            if pos.in_bounds(bounds) {
+                let (x, y) = self.grid_to_screen(pos, 0);
+                self.draw_cell_preview(cell, x, y, cell_size, 140);
+            }
+        }
+
+        for pane in 0..2 {
+            let (x, y) = self.grid_to_screen(cursor, pane);
+            let color = if pane == 0 {
+                SKYBLUE
+            } else {
+                Color::from_rgba(102, 191, 255, 100)
+            };
+            draw_rectangle_lines(x, y, cell_size, cell_size, 3.0, color);
+        }
+
+        let rect = self.pane_rect(0);
+        let text = format!(
+            "Cursor ({}, {}): Enter {}, Del erases, Shift+arrows select, Tab exits",
+            cursor.x,
+            cursor.y,
+            if self.keyboard_drag {
+                "drops"
+            } else {
+                "places"
+            },
+        );
+        draw_text(&text, rect.x + 6.0, rect.y + rect.h - 8.0, 16.0, SKYBLUE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::{Cell, Grid};
+    use crate::position::Position;
+
+    #[test]
+    fn cursor_clamped_after_shrinking_can_place_a_cell() {
+        let mut grid = Grid::create_empty(6, 5);
+        let cursor = Position::new(5, 4);
+        grid.resize(3, 2);
+
+        let cursor = cursor.clamped(grid.bounds());
+        assert_eq!(cursor, Position::new(2, 1));
+        *grid.at_mut(cursor) = Cell::Wall;
+        assert_eq!(grid.at(Position::new(2, 1)), Cell::Wall);
+    }
+}
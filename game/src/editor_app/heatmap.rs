@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::direction::Dir8;
+use crate::game::{Action, CyborgDistance, Phase, cyborg_distances, trace_action};
+use crate::grid::{Cell, Grid};
+use crate::position::Position;
+
+use super::Editor;
+
+/// Labels are only drawn when cells are at least this many pixels wide.
+const MIN_LABEL_CELL_SIZE: f32 = 28.0;
+
+/// Cyborg distances and intended rat moves for one state of the right pane.
+pub(super) struct Heatmap {
+    /// Cells the overlay was computed for, to detect when it's stale.
+    cells: Vec<Vec<Cell>>,
+    distances: HashMap<Position, CyborgDistance>,
+    /// Move each rat and cyborg rat would make if the player stalled, with whether it's a cyborg.
+    moves: Vec<(Position, Dir8, bool)>,
+}
+
+impl Heatmap {
+    fn new(grid: &Grid) -> Self {
+        let moves = trace_action(grid, Action::Stall)
+            .into_iter()
+            .filter_map(|step| match step.phase {
+                Phase::CyborgRat { pos, .. } => Some((pos, step.chosen?, true)),
+                Phase::Rat { pos, .. } => Some((pos, step.chosen?, false)),
+                _ => None,
+            })
+            .collect();
+        Self {
+            cells: grid.cells().to_vec(),
+            distances: cyborg_distances(grid),
+            moves,
+        }
+    }
+}
+
+/// Arrow from the center of one cell towards the center of another.
+fn draw_arrow(from: (f32, f32), to: (f32, f32), color: Color) {
+    let (fx, fy) = from;
+    let (tx, ty) = to;
+    draw_line(fx, fy, tx, ty, 3.0, color);
+
+    let dir = vec2(tx - fx, ty - fy).normalize_or_zero();
+    let head = dir * 8.0;
+    let side = vec2(-dir.y, dir.x) * 5.0;
+    draw_triangle(
+        vec2(tx, ty),
+        vec2(tx, ty) - head + side,
+        vec2(tx, ty) - head - side,
+        color,
+    );
+}
+
+impl Editor {
+    pub(super) fn toggle_heatmap(&mut self) {
+        self.heatmap_enabled = !self.heatmap_enabled;
+        self.heatmap = None;
+    }
+
+    /// Recompute the overlay if the game state changed.
+    pub(super) fn update_heatmap(&mut self) {
+        if !self.heatmap_enabled {
+            return;
+        }
+        let grid = self.shown_grid();
+        if !self
+            .heatmap
+            .as_ref()
+            .is_some_and(|h| h.cells.as_slice() == grid.cells())
+        {
+            self.heatmap = Some(Heatmap::new(grid));
+        }
+    }
+
+    /// Distance ramp with (ortho, diag) labels, and arrows for intended moves, on the right pane.
+    pub(super) fn render_heatmap(&self, cell_size: f32) {
+        let Some(heatmap) = &self.heatmap else {
+            return;
+        };
+
+        // Near cells are warm, far cells cool
+        let max_length = heatmap
+            .distances
+            .values()
+            .map(|d| d.length())
+            .fold(0.0, f32::max)
+            .max(1.0);
+        for (&pos, &distance) in &heatmap.distances {
+            let t = distance.length() / max_length;
+            let (x, y) = self.grid_to_screen(pos, 1);
+            draw_rectangle(x, y, cell_size, cell_size, Color::new(1.0 - t, 0.3, t, 0.3));
+
+            if cell_size >= MIN_LABEL_CELL_SIZE {
+                let label = distance.to_string();
+                let font_size = (cell_size * 0.3).min(16.0);
+                let dims = measure_text(&label, None, font_size as u16, 1.0);
+                draw_text(
+                    &label,
+                    x + (cell_size - dims.width) / 2.0,
+                    y + cell_size - 3.0,
+                    font_size,
+                    WHITE,
+                );
+            }
+        }
+
+        for &(pos, dir, cyborg) in &heatmap.moves {
+            let (x, y) = self.grid_to_screen(pos, 1);
+            let (tx, ty) = self.grid_to_screen(pos + dir.delta(), 1);
+            let half = cell_size / 2.0;
+            let color = if cyborg { SKYBLUE } else { ORANGE };
+            draw_arrow((x + half, y + half), (tx + half, ty + half), color);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs::{metadata, read_to_string};
+use std::time::SystemTime;
+
+use macroquad::prelude::*;
+
+use crate::game::Action;
+use crate::grid::{Grid, LevelMetadata};
+
+use super::{Editor, level_csv_path, level_json_path};
+
+/// Seconds between checks of the level files for changes.
+const CHECK_INTERVAL: f64 = 0.5;
+
+/// Modification times of a level's CSV and JSON files, None where a file is missing.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) struct LevelFileTimes {
+    csv: Option<SystemTime>,
+    json: Option<SystemTime>,
+}
+
+impl LevelFileTimes {
+    pub(super) fn read(level: &str) -> Self {
+        let modified = |path: String| metadata(path).and_then(|m| m.modified()).ok();
+        Self {
+            csv: modified(level_csv_path(level)),
+            json: modified(level_json_path(level)),
+        }
+    }
+}
+
+/// Read a level's grid, display name and saved solution, reporting what's wrong with the files.
+pub(super) fn read_level(level: &str) -> Result<(Grid, String, Option<Vec<Action>>), String> {
+    let csv = read_to_string(level_csv_path(level)).map_err(|e| e.to_string())?;
+    let json = read_to_string(level_json_path(level)).map_err(|e| e.to_string())?;
+    let metadata = LevelMetadata::try_parse(&json).map_err(|e| e.to_string())?;
+    let grid = Grid::try_from_csv_and_metadata(&csv, &metadata)?;
+    let solution = metadata.solution.as_deref().and_then(Action::parse_moves);
+    Ok((grid, metadata.name, solution))
+}
+
+impl Editor {
+    /// Remember the files as they are now, so our own writes don't trigger a reload.
+    pub(super) fn record_level_file_times(&mut self) {
+        self.level_file_times = LevelFileTimes::read(&self.level_path);
+    }
+
+    /// Reload the level if its files changed on disk since the last check.
+    pub(super) fn check_level_files(&mut self) {
+        let now = get_time();
+        if now - self.last_file_check < CHECK_INTERVAL {
+            return;
+        }
+        self.last_file_check = now;
+
+        let times = LevelFileTimes::read(&self.level_path);
+        if times == self.level_file_times {
+            return;
+        }
+        self.level_file_times = times;
+        self.reload_level();
+    }
+
+    /// Whether the level was changed since it was last loaded or saved.
+    fn has_unsaved_edits(&self) -> bool {
+        self.level_file_state
+            .as_ref()
+            .is_some_and(|state| !state.same_level(&self.recovery()))
+    }
+
+    /// Replace the level with the files on disk, keeping the move history.
+    /// Files that fail to parse (e.g. half-written) leave the level as is and show the error,
+    /// and so do unsaved edits, which saving would then write over the files.
+    fn reload_level(&mut self) {
+        if self.has_unsaved_edits() {
+            let notice = "Not reloaded: the level files changed, but there are unsaved edits";
+            self.file_error = Some(notice.to_string());
+            return;
+        }
+        let (grid, name, solution) = match read_level(&self.level_path) {
+            Ok(level) => level,
+            Err(e) => {
+                self.file_error = Some(format!("Reload failed: {}", e));
+                return;
+            }
+        };
+        self.file_error = None;
+
+        self.initial_grid = grid;
+        self.level_name = name;
+        self.saved_solution = solution;
+        self.level_file_state = Some(self.recovery());
+        // Drags and selections refer to the old grid, so drop them
+        self.dragging = None;
+        self.dragging_selection = None;
+        self.shape_drag = None;
+        self.clear_selection();
+        self.clamp_edit_cursor();
+        self.clamp_pan();
+        self.replay_inputs();
+    }
+}
@@ -0,0 +1,134 @@
+use macroquad::prelude::*;
+
+use super::Editor;
+use super::text_input::{TextInput, TextInputEvent};
+
+/// Editable level metadata fields, in panel order.
+#[derive(Clone, Copy, PartialEq)]
+enum MetadataField {
+    Name,
+}
+
+impl MetadataField {
+    fn all() -> [MetadataField; 1] {
+        [MetadataField::Name]
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MetadataField::Name => "Display name:",
+        }
+    }
+
+    fn accepts(self, c: char) -> bool {
+        match self {
+            MetadataField::Name => c.is_alphanumeric() || c.is_ascii_punctuation() || c == ' ',
+        }
+    }
+}
+
+/// Panel for editing the level's metadata. Tab moves between fields.
+pub(super) struct MetadataDialog {
+    fields: Vec<(MetadataField, TextInput)>,
+    focused: usize,
+}
+
+impl Editor {
+    pub(super) fn open_metadata_dialog(&mut self) {
+        let fields = MetadataField::all()
+            .into_iter()
+            .map(|field| {
+                let value = match field {
+                    MetadataField::Name => self.level_name.as_str(),
+                };
+                (field, TextInput::new(value, false))
+            })
+            .collect();
+        self.metadata_dialog = Some(MetadataDialog { fields, focused: 0 });
+    }
+
+    pub(super) fn handle_metadata_dialog_input(&mut self) {
+        let Some(dialog) = &mut self.metadata_dialog else {
+            return;
+        };
+
+        if is_key_pressed(KeyCode::Tab) {
+            let n = dialog.fields.len();
+            let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+            dialog.focused = if shift {
+                (dialog.focused + n - 1) % n
+            } else {
+                (dialog.focused + 1) % n
+            };
+        }
+
+        let (field, input) = &mut dialog.fields[dialog.focused];
+        let field = *field;
+        match input.handle_input(|c| field.accepts(c)) {
+            TextInputEvent::Cancel => self.metadata_dialog = None,
+            TextInputEvent::Submit => {
+                let dialog = self.metadata_dialog.take().unwrap();
+                for (field, input) in dialog.fields {
+                    match field {
+                        MetadataField::Name if !input.text().trim().is_empty() => {
+                            self.level_name = input.text().trim().to_string();
+                        }
+                        MetadataField::Name => {}
+                    }
+                }
+            }
+            TextInputEvent::None => {}
+        }
+    }
+
+    pub(super) fn render_metadata_dialog(&self) {
+        let Some(dialog) = &self.metadata_dialog else {
+            return;
+        };
+
+        let row_h = 60.0;
+        let dialog_w = 400.0;
+        let dialog_h = 60.0 + dialog.fields.len() as f32 * row_h;
+        let dialog_x = (screen_width() - dialog_w) / 2.0;
+        let dialog_y = (screen_height() - dialog_h) / 2.0;
+
+        // Dim background
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::from_rgba(0, 0, 0, 150),
+        );
+
+        // Dialog box
+        draw_rectangle(
+            dialog_x,
+            dialog_y,
+            dialog_w,
+            dialog_h,
+            Color::from_rgba(40, 40, 50, 255),
+        );
+        draw_rectangle_lines(dialog_x, dialog_y, dialog_w, dialog_h, 2.0, WHITE);
+
+        // Title
+        draw_text("Level info", dialog_x + 10.0, dialog_y + 30.0, 26.0, WHITE);
+
+        // Fields
+        for (i, (field, input)) in dialog.fields.iter().enumerate() {
+            let row_y = dialog_y + 40.0 + i as f32 * row_h;
+            let color = if i == dialog.focused { WHITE } else { GRAY };
+            draw_text(field.label(), dialog_x + 10.0, row_y + 16.0, 18.0, color);
+            input.draw(dialog_x + 10.0, row_y + 22.0, dialog_w - 20.0, 30.0, 22.0);
+        }
+
+        // Hint
+        draw_text(
+            "Tab to switch field, Enter to confirm, Esc to cancel",
+            dialog_x + 10.0,
+            dialog_y + dialog_h - 8.0,
+            14.0,
+            GRAY,
+        );
+    }
+}
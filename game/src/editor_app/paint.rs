@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use macroquad::prelude::*;
+
+use crate::position::{Position, PositionDelta};
+
+use super::{Editor, Tool};
+
+/// How a cell tool paints when clicking on the initial grid.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum PaintMode {
+    /// One cell at a time, following the mouse while dragging
+    Brush,
+    /// The contiguous region of cells equal to the clicked one
+    Fill,
+    /// Outline of the rectangle dragged out
+    Rect,
+    /// Every cell in the rectangle dragged out
+    FilledRect,
+}
+
+impl PaintMode {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            PaintMode::Brush => "Brush",
+            PaintMode::Fill => "Fill",
+            PaintMode::Rect => "Rect",
+            PaintMode::FilledRect => "Block",
+        }
+    }
+
+    pub(super) fn key(self) -> &'static str {
+        match self {
+            PaintMode::Brush => "b",
+            PaintMode::Fill => "f",
+            PaintMode::Rect | PaintMode::FilledRect => "k",
+        }
+    }
+
+    /// The mode after this one, for the toolbar button.
+    pub(super) fn next(self) -> PaintMode {
+        match self {
+            PaintMode::Brush => PaintMode::Fill,
+            PaintMode::Fill => PaintMode::Rect,
+            PaintMode::Rect => PaintMode::FilledRect,
+            PaintMode::FilledRect => PaintMode::Brush,
+        }
+    }
+}
+
+/// A shape being dragged out, painted when the mouse is released.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Shape {
+    Line,
+    Rect,
+    FilledRect,
+}
+
+impl Shape {
+    /// Cells covered by the shape between two corners (or end points), in drawing order.
+    pub(super) fn positions(self, start: Position, end: Position) -> Vec<Position> {
+        match self {
+            Shape::Line => line_positions(start, end),
+            Shape::Rect | Shape::FilledRect => {
+                let (min_x, max_x) = (start.x.min(end.x), start.x.max(end.x));
+                let (min_y, max_y) = (start.y.min(end.y), start.y.max(end.y));
+                let mut positions = Vec::new();
+                for y in min_y..=max_y {
+                    for x in min_x..=max_x {
+                        let edge = x == min_x || x == max_x || y == min_y || y == max_y;
+                        if edge || self == Shape::FilledRect {
+                            positions.push(Position { x, y });
+                        }
+                    }
+                }
+                positions
+            }
+        }
+    }
+}
+
+/// Bresenham line from `start` to `end`, inclusive.
+fn line_positions(start: Position, end: Position) -> Vec<Position> {
+    let dx = (end.x - start.x).abs();
+    let dy = -(end.y - start.y).abs();
+    let step_x = if start.x < end.x { 1 } else { -1 };
+    let step_y = if start.y < end.y { 1 } else { -1 };
+
+    let mut positions = Vec::new();
+    let mut pos = start;
+    let mut err = dx + dy;
+    loop {
+        positions.push(pos);
+        if pos == end {
+            return positions;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            pos.x += step_x;
+        }
+        if e2 <= dx {
+            err += dx;
+            pos.y += step_y;
+        }
+    }
+}
+
+impl Editor {
+    /// Positions in the 4-connected region of cells equal to the one at `start`.
+    fn fill_region(&self, start: Position) -> Vec<Position> {
+        let target = self.initial_grid.at(start);
+        let bounds = (self.initial_grid.width(), self.initial_grid.height());
+        let neighbors = [
+            PositionDelta::new(0, -1),
+            PositionDelta::new(0, 1),
+            PositionDelta::new(1, 0),
+            PositionDelta::new(-1, 0),
+        ];
+
+        let mut region = Vec::new();
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(pos) = stack.pop() {
+            region.push(pos);
+            for delta in neighbors {
+                let next = pos + delta;
+                if next.in_bounds(bounds)
+                    && self.initial_grid.at(next) == target
+                    && visited.insert(next)
+                {
+                    stack.push(next);
+                }
+            }
+        }
+        region
+    }
+
+    /// Paint `tool`'s cell at every position, then replay once.
+    fn paint_positions(&mut self, tool: Tool, positions: &[Position]) {
+        let bounds = (self.initial_grid.width(), self.initial_grid.height());
+        for &pos in positions {
+            if pos.in_bounds(bounds)
+                && let Some(cell) = tool.to_cell(pos, self.player_dir, self.trigger_digit)
+            {
+                self.put_cell_symmetric(pos, cell);
+            }
+        }
+        self.replay_inputs();
+    }
+
+    /// Flood fill from `pos` with the current tool.
+    pub(super) fn fill(&mut self, pos: Position) {
+        let region = self.fill_region(pos);
+        self.paint_positions(self.tool, &region);
+    }
+
+    pub(super) fn start_shape(&mut self, shape: Shape, pos: Position) {
+        self.shape_drag = Some((shape, pos, pos));
+    }
+
+    pub(super) fn update_shape(&mut self, pos: Position) {
+        if let Some((shape, start, _)) = self.shape_drag {
+            self.shape_drag = Some((shape, start, pos));
+        }
+    }
+
+    pub(super) fn end_shape(&mut self) {
+        if let Some((shape, start, end)) = self.shape_drag.take() {
+            self.paint_positions(self.tool, &shape.positions(start, end));
+        }
+    }
+
+    /// Ghost cells for the shape being dragged out, on the initial grid.
+    pub(super) fn render_shape_preview(&self, cell_size: f32) {
+        let Some((shape, start, end)) = self.shape_drag else {
+            return;
+        };
+
+        for pos in shape.positions(start, end) {
+            if let Some(cell) = self.tool.to_cell(pos, self.player_dir, self.trigger_digit) {
+                let (x, y) = self.grid_to_screen(pos, 0);
+                self.draw_cell_preview(cell, x, y, cell_size, 140);
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+use macroquad::prelude::*;
+
+use crate::grid::LevelMetadata;
+use crate::position::Position;
+
+use super::text_input::{TextInput, TextInputEvent};
+use super::{Editor, LEVELS_DIR, level_json_path};
+
+const MAX_SUGGESTIONS: usize = 6;
+
+/// Portal target dialog with completions from the level tree on disk.
+pub(super) struct PortalDialog {
+    pos: Position,
+    input: TextInput,
+    /// Every level under `levels/`, by path without extension.
+    levels: Vec<String>,
+    /// Portal targets of every level on disk, used to detect portal loops.
+    links: HashMap<String, Vec<String>>,
+    /// Index into the current suggestions, if one is highlighted.
+    selected: Option<usize>,
+}
+
+impl PortalDialog {
+    fn suggestions(&self) -> Vec<&str> {
+        let typed = self.input.text();
+        let mut prefixed: Vec<&str> = Vec::new();
+        let mut containing: Vec<&str> = Vec::new();
+        for level in &self.levels {
+            if level == typed {
+                continue;
+            }
+            let stem = level.rsplit('/').next().unwrap_or(level);
+            if level.starts_with(typed) || stem.starts_with(typed) {
+                prefixed.push(level);
+            } else if level.contains(typed) {
+                containing.push(level);
+            }
+        }
+        prefixed.extend(containing);
+        prefixed.truncate(MAX_SUGGESTIONS);
+        prefixed
+    }
+
+    fn target_exists(&self) -> bool {
+        self.levels.iter().any(|l| l == self.input.text())
+    }
+}
+
+/// Collect level names (paths relative to `dir`, without extension) of every CSV under `dir`.
+/// Like `build.rs`, skips directories starting with an underscore, which hold editor data.
+fn collect_levels(dir: &Path, prefix: &str, levels: &mut Vec<String>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let level_name = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if path.is_dir() {
+            if !name.starts_with('_') {
+                collect_levels(&path, &level_name, levels);
+            }
+        } else if path.extension().is_some_and(|e| e == "csv") {
+            levels.push(level_name);
+        }
+    }
+}
+
+impl Editor {
+    /// Open the portal dialog, pre-filled with the existing target if there is one.
+    pub(super) fn open_portal_dialog(&mut self, pos: Position) {
+        let current = self.initial_grid.get_portal(pos).unwrap_or("");
+
+        let mut levels = Vec::new();
+        collect_levels(Path::new(LEVELS_DIR), "", &mut levels);
+        levels.sort();
+
+        let links = levels
+            .iter()
+            .filter_map(|level| {
+                let json = read_to_string(level_json_path(level)).ok()?;
+                let metadata: LevelMetadata = serde_json::from_str(&json).ok()?;
+                Some((level.clone(), metadata.portals().into_values().collect()))
+            })
+            .collect();
+
+        self.portal_dialog = Some(PortalDialog {
+            pos,
+            input: TextInput::new(current, false),
+            levels,
+            links,
+            selected: None,
+        });
+    }
+
+    /// Returns true if entering `target` from this level can lead back here through portals,
+    /// which would let the level stack grow without bound.
+    fn portal_loops_back(&self, dialog: &PortalDialog, target: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![target];
+        while let Some(level) = stack.pop() {
+            if level == self.level_path {
+                return true;
+            }
+            if visited.insert(level) {
+                let next = dialog.links.get(level).into_iter().flatten();
+                stack.extend(next.map(String::as_str));
+            }
+        }
+        false
+    }
+
+    pub(super) fn handle_portal_dialog_input(&mut self) {
+        let Some(dialog) = &mut self.portal_dialog else {
+            return;
+        };
+
+        // Up/Down highlight a suggestion, Tab completes to it
+        let count = dialog.suggestions().len();
+        if is_key_pressed(KeyCode::Down) && count > 0 {
+            dialog.selected = Some(dialog.selected.map_or(0, |i| (i + 1) % count));
+        }
+        if is_key_pressed(KeyCode::Up) && count > 0 {
+            dialog.selected = Some(
+                dialog
+                    .selected
+                    .map_or(count - 1, |i| (i + count - 1) % count),
+            );
+        }
+        if is_key_pressed(KeyCode::Tab)
+            && let Some(completion) = dialog
+                .suggestions()
+                .get(dialog.selected.unwrap_or(0))
+                .map(|s| s.to_string())
+        {
+            dialog.input.set_text(&completion);
+            dialog.selected = None;
+        }
+
+        let highlighted = dialog
+            .selected
+            .and_then(|i| dialog.suggestions().get(i).map(|s| s.to_string()));
+        let before = dialog.input.text().to_string();
+        let event = dialog
+            .input
+            .handle_input(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ' || c == '/');
+        if dialog.input.text() != before {
+            dialog.selected = None;
+        }
+
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        match event {
+            TextInputEvent::Cancel => self.portal_dialog = None,
+            TextInputEvent::Submit if highlighted.is_some() || !dialog.input.text().is_empty() => {
+                // Enter on a highlighted suggestion picks it
+                if let Some(completion) = highlighted {
+                    dialog.input.set_text(&completion);
+                }
+                let level = dialog.input.text().to_string();
+                let create = ctrl && !dialog.target_exists();
+                let pos = dialog.pos;
+                self.portal_dialog = None;
+                if create {
+                    self.create_empty_level(&level);
+                }
+                self.place_portal(pos, level);
+            }
+            TextInputEvent::Submit | TextInputEvent::None => {}
+        }
+    }
+
+    pub(super) fn render_portal_dialog(&self) {
+        let Some(dialog) = &self.portal_dialog else {
+            return;
+        };
+
+        let suggestions = dialog.suggestions();
+        let target = dialog.input.text();
+
+        let dialog_w = 360.0;
+        let dialog_h = 150.0 + MAX_SUGGESTIONS as f32 * 20.0;
+        let dialog_x = (screen_width() - dialog_w) / 2.0;
+        let dialog_y = (screen_height() - dialog_h) / 2.0;
+
+        // Dim background
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::from_rgba(0, 0, 0, 150),
+        );
+
+        // Dialog box
+        draw_rectangle(
+            dialog_x,
+            dialog_y,
+            dialog_w,
+            dialog_h,
+            Color::from_rgba(40, 40, 50, 255),
+        );
+        draw_rectangle_lines(dialog_x, dialog_y, dialog_w, dialog_h, 2.0, WHITE);
+
+        // Title
+        draw_text(
+            "Enter level name:",
+            dialog_x + 10.0,
+            dialog_y + 30.0,
+            26.0,
+            WHITE,
+        );
+
+        // Text input box
+        dialog.input.draw(
+            dialog_x + 10.0,
+            dialog_y + 45.0,
+            dialog_w - 20.0,
+            30.0,
+            22.0,
+        );
+
+        // Suggestions
+        let list_y = dialog_y + 80.0;
+        for (i, suggestion) in suggestions.iter().enumerate() {
+            let row_y = list_y + i as f32 * 20.0;
+            if dialog.selected == Some(i) {
+                draw_rectangle(
+                    dialog_x + 10.0,
+                    row_y,
+                    dialog_w - 20.0,
+                    20.0,
+                    Color::from_rgba(80, 80, 100, 255),
+                );
+            }
+            draw_text(suggestion, dialog_x + 15.0, row_y + 15.0, 18.0, LIGHTGRAY);
+        }
+
+        // Validation
+        let status_y = list_y + MAX_SUGGESTIONS as f32 * 20.0 + 20.0;
+        if !target.is_empty() {
+            let (message, color) = if !dialog.target_exists() {
+                ("Level does not exist (Ctrl+Enter creates it)", RED)
+            } else if self.portal_loops_back(dialog, target) {
+                ("Warning: leads back to this level (portal loop)", ORANGE)
+            } else {
+                ("", WHITE)
+            };
+            draw_text(message, dialog_x + 10.0, status_y, 16.0, color);
+        }
+
+        // Hint
+        draw_text(
+            "Enter to confirm, Up/Down/Tab to complete, Esc to cancel",
+            dialog_x + 10.0,
+            dialog_y + dialog_h - 10.0,
+            14.0,
+            GRAY,
+        );
+    }
+}
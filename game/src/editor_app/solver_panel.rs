@@ -0,0 +1,119 @@
+use macroquad::prelude::*;
+
+use crate::game::{SOLUTION_COUNT_CAP, Solver, SolverStatus};
+
+use super::{Editor, PADDING};
+
+/// Seconds of each frame the solver may spend searching.
+const SOLVER_FRAME_BUDGET: f64 = 0.008;
+/// States expanded between time checks.
+const SOLVER_STEP: usize = 50;
+
+impl Editor {
+    /// Turn the solver panel on or off. While on, the level is re-solved after every edit.
+    pub(super) fn toggle_solver(&mut self) {
+        self.solver_enabled = !self.solver_enabled;
+        self.solver = None;
+        self.solution_index = 0;
+    }
+
+    /// Restart the search if the initial grid changed, then search for part of this frame.
+    pub(super) fn update_solver(&mut self) {
+        if !self.solver_enabled {
+            return;
+        }
+
+        if !self
+            .solver
+            .as_ref()
+            .is_some_and(|s| s.is_for(&self.initial_grid))
+        {
+            self.solver = Some(Solver::new(&self.initial_grid));
+            self.solution_index = 0;
+        }
+
+        let solver = self.solver.as_mut().unwrap();
+        let start = get_time();
+        while !solver.step(SOLVER_STEP) && get_time() - start < SOLVER_FRAME_BUDGET {}
+    }
+
+    /// Replace the move history with the next shortest solution, cycling through them.
+    pub(super) fn load_next_solution(&mut self) {
+        let Some(solver) = &self.solver else {
+            return;
+        };
+        let count = solver.solution_count();
+        if count == 0 {
+            return;
+        }
+
+        let k = self.solution_index % count;
+        if let Some(solution) = solver.solution(k) {
+            self.input_history = solution;
+            self.replay_inputs();
+            self.solution_index = k + 1;
+        }
+    }
+
+    /// Solver results, drawn at the top of the right pane.
+    pub(super) fn render_solver_status(&self) {
+        if !self.solver_enabled {
+            return;
+        }
+        let Some(solver) = &self.solver else {
+            return;
+        };
+
+        let (pane_width, _, _) = self.pane_layout();
+        let pane_x = self.pane_rect(1).x;
+
+        let count = solver.solution_count();
+        let count_text = if count >= SOLUTION_COUNT_CAP {
+            format!("{}+", SOLUTION_COUNT_CAP)
+        } else {
+            count.to_string()
+        };
+        let (text, color) = match solver.status() {
+            SolverStatus::Searching => (
+                format!("Solving... {} states", solver.states_explored()),
+                LIGHTGRAY,
+            ),
+            SolverStatus::Solved => (
+                format!(
+                    "Shortest: {} moves, {} of that length [F6] load {}/{}",
+                    solver.shortest().unwrap(),
+                    count_text,
+                    self.solution_index % count + 1,
+                    count_text,
+                ),
+                GREEN,
+            ),
+            SolverStatus::PartlySolved => (
+                format!(
+                    "Shortest: {} moves, {} of that length within {} states [F6] load {}/{}",
+                    solver.shortest().unwrap(),
+                    count_text,
+                    solver.states_explored(),
+                    self.solution_index % count + 1,
+                    count_text,
+                ),
+                ORANGE,
+            ),
+            SolverStatus::Unsolvable => ("No solution".to_string(), RED),
+            SolverStatus::LimitReached => (
+                format!("No solution within {} states", solver.states_explored()),
+                ORANGE,
+            ),
+            SolverStatus::NoRats => ("No rats to solve for".to_string(), GRAY),
+        };
+
+        let dims = measure_text(&text, None, 18, 1.0);
+        draw_text(
+            &text,
+            pane_x + pane_width - dims.width - 4.0,
+            PADDING + 20.0,
+            18.0,
+            color,
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+use macroquad::prelude::*;
+
+use crate::grid::{Cell, Grid, LevelMetadata};
+use crate::position::{Position, PositionDelta};
+
+use super::text_input::{TextInput, TextInputEvent};
+use super::{Editor, LEVELS_DIR, PADDING, Rect, TOOLBAR_WIDTH, Tool, write_level};
+
+/// Stamps are saved as level fragments in this directory under `levels/`.
+/// `build.rs` and the portal dialog skip it because it starts with an underscore.
+const STAMPS_DIR: &str = "_stamps";
+const PALETTE_WIDTH: f32 = 160.0;
+const PALETTE_ROW_HEIGHT: f32 = 30.0;
+
+/// A reusable piece of a level, with positions relative to its top-left corner.
+/// Empty cells are transparent: placing a stamp leaves what's under them.
+pub(super) struct Stamp {
+    name: String,
+    cells: Vec<(PositionDelta, Cell)>,
+    /// Portals with the levels they require
+    portals: Vec<(PositionDelta, String, Vec<String>)>,
+    notes: Vec<(PositionDelta, String)>,
+}
+
+impl Stamp {
+    /// Players are left out, since a level has only one.
+    fn from_grid(name: &str, grid: &Grid) -> Self {
+        let origin = Position::new(0, 0);
+        Self {
+            name: name.to_string(),
+            cells: grid
+                .entries()
+                .filter(|(_, cell)| !matches!(cell, Cell::Empty | Cell::Player(_)))
+                .map(|(pos, cell)| (pos - origin, cell))
+                .collect(),
+            portals: grid
+                .portals()
+                .map(|(pos, level)| {
+                    let requires = grid.portal_requires(pos).to_vec();
+                    (pos - origin, level.to_string(), requires)
+                })
+                .collect(),
+            notes: grid
+                .notes()
+                .map(|(pos, text)| (pos - origin, text.to_string()))
+                .collect(),
+        }
+    }
+
+    /// The stamp mirrored left-right if `mirror`, then turned `turns` quarter turns clockwise,
+    /// moved back so its top-left corner is at the origin.
+    fn transformed(&self, turns: u8, mirror: bool) -> Self {
+        let delta = |mut d: PositionDelta| {
+            if mirror {
+                d.dx = -d.dx;
+            }
+            for _ in 0..turns {
+                d = PositionDelta::new(-d.dy, d.dx);
+            }
+            d
+        };
+        let cell = |mut c: Cell| {
+            c = c.flipped(mirror, false);
+            for _ in 0..turns {
+                c = c.rotated_cw();
+            }
+            c
+        };
+
+        let mut stamp = Self {
+            name: self.name.clone(),
+            cells: self
+                .cells
+                .iter()
+                .map(|&(d, c)| (delta(d), cell(c)))
+                .collect(),
+            portals: self
+                .portals
+                .iter()
+                .map(|(d, l, r)| (delta(*d), l.clone(), r.clone()))
+                .collect(),
+            notes: self
+                .notes
+                .iter()
+                .map(|(d, t)| (delta(*d), t.clone()))
+                .collect(),
+        };
+
+        let deltas = stamp
+            .cells
+            .iter()
+            .map(|(d, _)| *d)
+            .chain(stamp.portals.iter().map(|(d, _, _)| *d))
+            .chain(stamp.notes.iter().map(|(d, _)| *d));
+        let min_x = deltas.clone().map(|d| d.dx).min().unwrap_or(0);
+        let min_y = deltas.map(|d| d.dy).min().unwrap_or(0);
+        let shift = |d: &mut PositionDelta| {
+            d.dx -= min_x;
+            d.dy -= min_y;
+        };
+        stamp.cells.iter_mut().for_each(|(d, _)| shift(d));
+        stamp.portals.iter_mut().for_each(|(d, _, _)| shift(d));
+        stamp.notes.iter_mut().for_each(|(d, _)| shift(d));
+        stamp
+    }
+}
+
+/// Every stamp under `levels/_stamps/`, sorted by name. Unreadable ones are skipped.
+fn load_stamps() -> Vec<Stamp> {
+    let dir = Path::new(LEVELS_DIR).join(STAMPS_DIR);
+    let Ok(entries) = read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut stamps: Vec<Stamp> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "csv"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            let read = || -> Result<Grid, String> {
+                let csv = read_to_string(&path).map_err(|e| e.to_string())?;
+                let json =
+                    read_to_string(path.with_extension("json")).map_err(|e| e.to_string())?;
+                let metadata = LevelMetadata::try_parse(&json).map_err(|e| e.to_string())?;
+                Grid::fragment_from_csv_and_metadata(&csv, &metadata)
+            };
+            read()
+                .map(|grid| Stamp::from_grid(&name, &grid))
+                .map_err(|e| log::warn!("Failed to load stamp {}: {}", path.display(), e))
+                .ok()
+        })
+        .collect();
+    stamps.sort_by(|a, b| a.name.cmp(&b.name));
+    stamps
+}
+
+/// Palette row `index`: the save button, then one row per stamp.
+/// The palette has its own column between the toolbar and the panes.
+fn palette_row_rect(index: usize) -> Rect {
+    Rect {
+        x: TOOLBAR_WIDTH + PADDING,
+        y: PADDING + index as f32 * (PALETTE_ROW_HEIGHT + 4.0),
+        w: PALETTE_WIDTH,
+        h: PALETTE_ROW_HEIGHT,
+    }
+}
+
+impl Editor {
+    /// Width the stamp palette takes from the panes while the Stamp tool is selected.
+    pub(super) fn palette_column_width(&self) -> f32 {
+        if self.tool == Tool::Stamp {
+            PALETTE_WIDTH + PADDING
+        } else {
+            0.0
+        }
+    }
+
+    pub(super) fn reload_stamps(&mut self) {
+        self.stamps = load_stamps();
+        if self.stamp_index.is_some_and(|i| i >= self.stamps.len()) {
+            self.stamp_index = None;
+        }
+    }
+
+    /// Selected stamp with the current rotation and mirroring applied.
+    fn current_stamp(&self) -> Option<Stamp> {
+        let stamp = self.stamps.get(self.stamp_index?)?;
+        Some(stamp.transformed(self.stamp_turns, self.stamp_mirror))
+    }
+
+    pub(super) fn rotate_stamp(&mut self, clockwise: bool) {
+        self.stamp_turns = if clockwise {
+            (self.stamp_turns + 1) % 4
+        } else {
+            (self.stamp_turns + 3) % 4
+        };
+    }
+
+    pub(super) fn mirror_stamp(&mut self) {
+        self.stamp_mirror = !self.stamp_mirror;
+    }
+
+    /// Stamp the selected stamp with its top-left corner at `pos`.
+    /// Cells follow the symmetry setting; portals and notes are placed once.
+    pub(super) fn place_stamp(&mut self, pos: Position) {
+        let Some(stamp) = self.current_stamp() else {
+            return;
+        };
+        let bounds = (self.initial_grid.width(), self.initial_grid.height());
+        for (delta, cell) in stamp.cells {
+            if (pos + delta).in_bounds(bounds) {
+                self.put_cell_symmetric(pos + delta, cell);
+            }
+        }
+        for (delta, level, requires) in stamp.portals {
+            if (pos + delta).in_bounds(bounds) {
+                self.initial_grid.insert_portal(pos + delta, level);
+                self.initial_grid.set_portal_requires(pos + delta, requires);
+            }
+        }
+        for (delta, text) in stamp.notes {
+            if (pos + delta).in_bounds(bounds) {
+                self.initial_grid.insert_note(pos + delta, text);
+            }
+        }
+        self.replay_inputs();
+    }
+
+    /// The selected cells cut out of the initial grid, cropped to their bounding box.
+    fn selection_fragment(&self) -> Option<Grid> {
+        let min_x = self.selection.iter().map(|p| p.x).min()?;
+        let min_y = self.selection.iter().map(|p| p.y).min()?;
+        let max_x = self.selection.iter().map(|p| p.x).max()?;
+        let max_y = self.selection.iter().map(|p| p.y).max()?;
+        let origin = Position { x: min_x, y: min_y };
+
+        let mut fragment =
+            Grid::create_empty((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize);
+        let top_left = Position::new(0, 0);
+        for &pos in &self.selection {
+            let target = top_left + (pos - origin);
+            let cell = self.initial_grid.at(pos);
+            if !matches!(cell, Cell::Player(_)) {
+                *fragment.at_mut(target) = cell;
+            }
+            if let Some(level) = self.initial_grid.get_portal(pos) {
+                fragment.insert_portal(target, level.to_string());
+                let requires = self.initial_grid.portal_requires(pos).to_vec();
+                fragment.set_portal_requires(target, requires);
+            }
+            if let Some(text) = self.initial_grid.get_note(pos) {
+                fragment.insert_note(target, text.to_string());
+            }
+        }
+        Some(fragment)
+    }
+
+    /// Write the selection as a stamp and select it.
+    fn save_stamp(&mut self, name: &str) {
+        let Some(fragment) = self.selection_fragment() else {
+            return;
+        };
+        let path = format!("{}/{}", STAMPS_DIR, name);
+        if let Err(e) = write_level(&path, &fragment, &fragment.metadata(name)) {
+            self.file_error = Some(format!("Saving stamp {} failed: {}", name, e));
+            return;
+        }
+        self.reload_stamps();
+        self.stamp_index = self.stamps.iter().position(|s| s.name == name);
+        self.stamp_turns = 0;
+        self.stamp_mirror = false;
+        self.tool = Tool::Stamp;
+    }
+
+    /// Clicks on the stamp palette, shown while the Stamp tool is selected.
+    /// Returns true if the click was on the palette.
+    pub(super) fn click_stamp_palette(&mut self, mx: f32, my: f32) -> bool {
+        if self.tool != Tool::Stamp {
+            return false;
+        }
+        if palette_row_rect(0).contains(mx, my) {
+            if !self.selection.is_empty() {
+                self.stamp_dialog = Some(TextInput::new("", false));
+            }
+            return true;
+        }
+        for i in 0..self.stamps.len() {
+            if palette_row_rect(i + 1).contains(mx, my) {
+                self.stamp_index = Some(i);
+                self.stamp_turns = 0;
+                self.stamp_mirror = false;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub(super) fn handle_stamp_dialog_input(&mut self) {
+        let Some(input) = &mut self.stamp_dialog else {
+            return;
+        };
+        // Names become file names
+        match input.handle_input(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            TextInputEvent::Cancel => self.stamp_dialog = None,
+            TextInputEvent::Submit if !input.text().is_empty() => {
+                let name = input.text().to_string();
+                self.stamp_dialog = None;
+                self.save_stamp(&name);
+            }
+            TextInputEvent::Submit | TextInputEvent::None => {}
+        }
+    }
+
+    pub(super) fn render_stamp_palette(&self) {
+        if self.tool != Tool::Stamp {
+            return;
+        }
+
+        let save = palette_row_rect(0);
+        let (label, color) = if self.selection.is_empty() {
+            ("Select to save", GRAY)
+        } else {
+            ("+ Save selection", WHITE)
+        };
+        draw_rectangle(
+            save.x,
+            save.y,
+            save.w,
+            save.h,
+            Color::from_rgba(40, 60, 50, 240),
+        );
+        draw_text(label, save.x + 8.0, save.y + 20.0, 18.0, color);
+
+        for (i, stamp) in self.stamps.iter().enumerate() {
+            let rect = palette_row_rect(i + 1);
+            let bg_color = if self.stamp_index == Some(i) {
+                Color::from_rgba(80, 80, 100, 240)
+            } else {
+                Color::from_rgba(50, 50, 60, 240)
+            };
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, bg_color);
+            draw_text(&stamp.name, rect.x + 8.0, rect.y + 20.0, 18.0, WHITE);
+        }
+
+        let hint_y = palette_row_rect(self.stamps.len() + 1).y + 14.0;
+        let orientation = format!(
+            "Rotation {}{}",
+            self.stamp_turns as u32 * 90,
+            if self.stamp_mirror { ", mirrored" } else { "" }
+        );
+        draw_text(&orientation, save.x, hint_y, 16.0, LIGHTGRAY);
+        draw_text(
+            "Wheel rotates, [h] mirrors",
+            save.x,
+            hint_y + 16.0,
+            16.0,
+            GRAY,
+        );
+    }
+
+    /// Ghost of the selected stamp at the grid cell under the mouse, or at the edit cursor.
+    pub(super) fn render_stamp_preview(&self, cell_size: f32) {
+        if self.tool != Tool::Stamp {
+            return;
+        }
+        let Some(stamp) = self.current_stamp() else {
+            return;
+        };
+        let (mx, my) = mouse_position();
+        let pos = match self.edit_cursor {
+            Some(cursor) => cursor,
+            None => match self.screen_to_grid(mx, my) {
+                Some((pos, 0)) => pos,
+                _ => return,
+            },
+        };
+
+        let bounds = (self.initial_grid.width(), self.initial_grid.height());
+        for (delta, cell) in &stamp.cells {
+            if (pos + *delta).in_bounds(bounds) {
+                let (x, y) = self.grid_to_screen(pos + *delta, 0);
+                self.draw_cell_preview(*cell, x, y, cell_size, 140);
+            }
+        }
+        let marked = stamp.portals.iter().map(|(delta, _, _)| delta);
+        for delta in marked.chain(stamp.notes.iter().map(|(delta, _)| delta)) {
+            if (pos + *delta).in_bounds(bounds) {
+                let (x, y) = self.grid_to_screen(pos + *delta, 0);
+                draw_rectangle_lines(x, y, cell_size, cell_size, 2.0, PURPLE);
+            }
+        }
+    }
+
+    pub(super) fn render_stamp_dialog(&self) {
+        let Some(input) = &self.stamp_dialog else {
+            return;
+        };
+
+        let dialog_w = 400.0;
+        let dialog_h = 130.0;
+        let dialog_x = (screen_width() - dialog_w) / 2.0;
+        let dialog_y = (screen_height() - dialog_h) / 2.0;
+
+        // Dim background
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::from_rgba(0, 0, 0, 150),
+        );
+
+        // Dialog box
+        draw_rectangle(
+            dialog_x,
+            dialog_y,
+            dialog_w,
+            dialog_h,
+            Color::from_rgba(40, 40, 50, 255),
+        );
+        draw_rectangle_lines(dialog_x, dialog_y, dialog_w, dialog_h, 2.0, WHITE);
+
+        draw_text("Stamp name:", dialog_x + 10.0, dialog_y + 30.0, 26.0, WHITE);
+        input.draw(
+            dialog_x + 10.0,
+            dialog_y + 45.0,
+            dialog_w - 20.0,
+            40.0,
+            20.0,
+        );
+        draw_text(
+            "Enter to save, Esc to cancel",
+            dialog_x + 10.0,
+            dialog_y + dialog_h - 15.0,
+            16.0,
+            GRAY,
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+
+use crate::grid::Cell;
+use crate::position::Position;
+
+use super::Editor;
+
+/// Mirror-painting mode: edits are repeated at the symmetric positions.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Symmetry {
+    Off,
+    /// Mirrored left-right, across the vertical center line
+    Horizontal,
+    /// Mirrored top-bottom, across the horizontal center line
+    Vertical,
+    /// Mirrored both ways
+    FourWay,
+    /// Rotated half a turn around the center
+    Rotational,
+}
+
+impl Symmetry {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Symmetry::Off => "Off",
+            Symmetry::Horizontal => "H",
+            Symmetry::Vertical => "V",
+            Symmetry::FourWay => "4-way",
+            Symmetry::Rotational => "Rot",
+        }
+    }
+
+    pub(super) fn next(self) -> Symmetry {
+        match self {
+            Symmetry::Off => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::FourWay,
+            Symmetry::FourWay => Symmetry::Rotational,
+            Symmetry::Rotational => Symmetry::Off,
+        }
+    }
+
+    /// The flips (left-right, top-bottom) whose images get painted, including the original.
+    fn flips(self) -> &'static [(bool, bool)] {
+        match self {
+            Symmetry::Off => &[(false, false)],
+            Symmetry::Horizontal => &[(false, false), (true, false)],
+            Symmetry::Vertical => &[(false, false), (false, true)],
+            Symmetry::FourWay => &[(false, false), (true, false), (false, true), (true, true)],
+            // A half turn is a flip both ways
+            Symmetry::Rotational => &[(false, false), (true, true)],
+        }
+    }
+}
+
+impl Editor {
+    /// `pos` and its symmetric images with the flips that produce them, without duplicates.
+    pub(super) fn symmetric_positions(&self, pos: Position) -> Vec<(Position, bool, bool)> {
+        let max_x = self.initial_grid.width() as i32 - 1;
+        let max_y = self.initial_grid.height() as i32 - 1;
+
+        let mut images: Vec<(Position, bool, bool)> = Vec::new();
+        for &(flip_x, flip_y) in self.symmetry.flips() {
+            let image = Position {
+                x: if flip_x { max_x - pos.x } else { pos.x },
+                y: if flip_y { max_y - pos.y } else { pos.y },
+            };
+            if !images.iter().any(|&(p, _, _)| p == image) {
+                images.push((image, flip_x, flip_y));
+            }
+        }
+        images
+    }
+
+    /// Set a cell and its symmetric images without replaying moves.
+    /// Players aren't mirrored, since a level has only one.
+    pub(super) fn put_cell_symmetric(&mut self, pos: Position, cell: Cell) {
+        if matches!(cell, Cell::Player(_)) {
+            self.put_cell(pos, cell);
+            return;
+        }
+        for (image, flip_x, flip_y) in self.symmetric_positions(pos) {
+            self.put_cell(image, cell.flipped(flip_x, flip_y));
+        }
+    }
+
+    /// Mirror lines (or the center point for rotation) over the initial grid.
+    pub(super) fn render_symmetry(&self, cell_size: f32) {
+        if self.symmetry == Symmetry::Off {
+            return;
+        }
+
+        let (x, y) = self.grid_to_screen(Position { x: 0, y: 0 }, 0);
+        let w = self.initial_grid.width() as f32 * cell_size;
+        let h = self.initial_grid.height() as f32 * cell_size;
+        let color = Color::from_rgba(255, 200, 80, 160);
+
+        if matches!(self.symmetry, Symmetry::Horizontal | Symmetry::FourWay) {
+            draw_line(x + w / 2.0, y, x + w / 2.0, y + h, 2.0, color);
+        }
+        if matches!(self.symmetry, Symmetry::Vertical | Symmetry::FourWay) {
+            draw_line(x, y + h / 2.0, x + w, y + h / 2.0, 2.0, color);
+        }
+        if self.symmetry == Symmetry::Rotational {
+            draw_circle_lines(x + w / 2.0, y + h / 2.0, cell_size / 3.0, 2.0, color);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use macroquad::prelude::*;
+
+/// Outcome of feeding one frame of keyboard input to a `TextInput`.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum TextInputEvent {
+    None,
+    Submit,
+    Cancel,
+}
+
+/// Editable text field with a cursor that can be moved within the text.
+///
+/// Multi-line fields insert explicit line breaks with Shift+Enter; plain Enter always submits.
+pub(super) struct TextInput {
+    text: String,
+    /// Byte offset into `text`, always on a char boundary.
+    cursor: usize,
+    multiline: bool,
+}
+
+impl TextInput {
+    /// Create a field pre-filled with `text`, with the cursor at the end.
+    pub(super) fn new(text: &str, multiline: bool) -> Self {
+        Self {
+            text: text.to_string(),
+            cursor: text.len(),
+            multiline,
+        }
+    }
+
+    pub(super) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text and move the cursor to the end.
+    pub(super) fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    /// Handle this frame's keys. `accept` filters which typed characters are inserted.
+    pub(super) fn handle_input(&mut self, accept: impl Fn(char) -> bool) -> TextInputEvent {
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+        if is_key_pressed(KeyCode::Escape) {
+            return TextInputEvent::Cancel;
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            if self.multiline && shift {
+                self.insert('\n');
+            } else {
+                return TextInputEvent::Submit;
+            }
+        }
+
+        if is_key_pressed(KeyCode::Backspace) {
+            self.backspace();
+        }
+        if is_key_pressed(KeyCode::Delete) {
+            self.delete();
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.move_left();
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.move_right();
+        }
+        if is_key_pressed(KeyCode::Home) {
+            self.cursor = self.line_start(self.cursor);
+        }
+        if is_key_pressed(KeyCode::End) {
+            self.cursor = self.line_end(self.cursor);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.move_vertical(false);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.move_vertical(true);
+        }
+
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && accept(c) {
+                self.insert(c);
+            }
+        }
+
+        TextInputEvent::None
+    }
+
+    fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    fn line_start(&self, at: usize) -> usize {
+        self.text[..at].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, at: usize) -> usize {
+        self.text[at..]
+            .find('\n')
+            .map_or(self.text.len(), |i| at + i)
+    }
+
+    /// Move to the previous or next explicit line, keeping the column (in chars) where possible.
+    fn move_vertical(&mut self, down: bool) {
+        let start = self.line_start(self.cursor);
+        let column = self.text[start..self.cursor].chars().count();
+
+        let target_start = if down {
+            let end = self.line_end(self.cursor);
+            if end == self.text.len() {
+                return;
+            }
+            end + 1
+        } else {
+            if start == 0 {
+                return;
+            }
+            self.line_start(start - 1)
+        };
+
+        let target_end = self.line_end(target_start);
+        self.cursor = self.text[target_start..target_end]
+            .char_indices()
+            .nth(column)
+            .map_or(target_end, |(i, _)| target_start + i);
+    }
+
+    /// Draw the text inside a box, wrapping long lines and scrolling to keep the cursor visible.
+    pub(super) fn draw(&self, x: f32, y: f32, w: f32, h: f32, font_size: f32) {
+        draw_rectangle(x, y, w, h, Color::from_rgba(20, 20, 30, 255));
+        draw_rectangle_lines(x, y, w, h, 1.0, GRAY);
+
+        let line_height = font_size * 1.15;
+        let max_width = w - 10.0;
+        let rows = self.visual_rows(font_size, max_width);
+        let max_rows = ((h - 4.0) / line_height).floor().max(1.0) as usize;
+
+        // Place the cursor at the end of a wrapped row rather than the start of the next one
+        let cursor_row = rows
+            .iter()
+            .position(|&(start, end)| start <= self.cursor && self.cursor <= end)
+            .unwrap_or(0);
+        let first_row = (cursor_row + 1).saturating_sub(max_rows);
+
+        for (i, &(start, end)) in rows.iter().enumerate().skip(first_row).take(max_rows) {
+            let row_y = y + 2.0 + (i - first_row) as f32 * line_height;
+            let baseline = row_y + font_size * 0.85;
+            draw_text(&self.text[start..end], x + 5.0, baseline, font_size, WHITE);
+
+            if i == cursor_row {
+                let prefix = &self.text[start..self.cursor];
+                let cursor_x = x + 5.0 + measure_text(prefix, None, font_size as u16, 1.0).width;
+                draw_line(
+                    cursor_x,
+                    row_y + 1.0,
+                    cursor_x,
+                    row_y + line_height - 1.0,
+                    1.0,
+                    WHITE,
+                );
+            }
+        }
+    }
+
+    /// Split the text into byte ranges that each fit on one row of the box.
+    fn visual_rows(&self, font_size: f32, max_width: f32) -> Vec<(usize, usize)> {
+        let mut rows = Vec::new();
+        let mut line_start = 0;
+        for line in self.text.split('\n') {
+            let mut row_start = line_start;
+            for (i, c) in line.char_indices() {
+                let pos = line_start + i;
+                let candidate = &self.text[row_start..pos + c.len_utf8()];
+                if pos > row_start
+                    && measure_text(candidate, None, font_size as u16, 1.0).width > max_width
+                {
+                    rows.push((row_start, pos));
+                    row_start = pos;
+                }
+            }
+            rows.push((row_start, line_start + line.len()));
+            line_start += line.len() + 1;
+        }
+        rows
+    }
+}
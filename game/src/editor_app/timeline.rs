@@ -0,0 +1,211 @@
+use macroquad::prelude::*;
+
+use crate::game::{Action, PlayState};
+use crate::grid::Grid;
+
+use super::{Editor, PADDING, Rect};
+
+/// Height of the timeline strip below the panes.
+pub(super) const TIMELINE_HEIGHT: f32 = 48.0;
+/// Width of one move in the timeline, before shrinking to fit.
+const MOVE_WIDTH: f32 = 18.0;
+const MIN_MOVE_WIDTH: f32 = 10.0;
+
+impl Editor {
+    /// Number of moves applied in the state shown in the right pane.
+    pub(super) fn timeline_index(&self) -> usize {
+        let last = self.game.state.history.len() - 1;
+        self.timeline_cursor.unwrap_or(last).min(last)
+    }
+
+    /// Grid shown in the right pane: the state after the moves up to the timeline cursor.
+    pub(super) fn shown_grid(&self) -> &Grid {
+        &self.game.state.history[self.timeline_index()]
+    }
+
+    /// Play state of the shown grid. Earlier states were all still being played.
+    pub(super) fn shown_play_state(&self) -> PlayState {
+        if self.timeline_index() + 1 == self.game.state.history.len() {
+            self.game.state.play_state()
+        } else {
+            PlayState::Playing
+        }
+    }
+
+    /// Show the state after `index` moves. The end of the history follows new moves.
+    pub(super) fn set_timeline_cursor(&mut self, index: usize) {
+        self.timeline_cursor = (index < self.input_history.len()).then_some(index);
+    }
+
+    pub(super) fn step_timeline(&mut self, forward: bool) {
+        let index = self.timeline_cursor.unwrap_or(self.input_history.len());
+        let index = if forward {
+            index + 1
+        } else {
+            index.saturating_sub(1)
+        };
+        self.set_timeline_cursor(index);
+    }
+
+    /// Drop every move after the timeline cursor.
+    pub(super) fn truncate_at_cursor(&mut self) {
+        if let Some(index) = self.timeline_cursor.take() {
+            self.input_history.truncate(index);
+            self.replay_inputs();
+        }
+    }
+
+    /// Insert a move at the timeline cursor, or append it when following the end.
+    pub(super) fn insert_input(&mut self, input: Action) {
+        match self.timeline_cursor {
+            Some(index) => {
+                self.input_history.insert(index, input);
+                self.replay_inputs();
+                self.set_timeline_cursor(index + 1);
+            }
+            None => self.add_input(input),
+        }
+    }
+
+    /// Remove the move just before the timeline cursor.
+    pub(super) fn remove_input_before_cursor(&mut self) {
+        match self.timeline_cursor {
+            Some(0) => {}
+            Some(index) => {
+                self.input_history.remove(index - 1);
+                self.replay_inputs();
+                self.set_timeline_cursor(index - 1);
+            }
+            None => self.remove_last_input(),
+        }
+    }
+
+    fn timeline_rect(&self) -> Rect {
+        Rect {
+            x: self.panes_x(),
+            y: screen_height() - PADDING - TIMELINE_HEIGHT,
+            w: screen_width() - self.panes_x() - PADDING,
+            h: TIMELINE_HEIGHT,
+        }
+    }
+
+    /// Width of each move and index of the first one shown, scrolled to keep the cursor visible.
+    fn timeline_layout(&self) -> (f32, usize) {
+        let rect = self.timeline_rect();
+        let count = self.input_history.len();
+        let width = (rect.w / (count + 1) as f32).clamp(MIN_MOVE_WIDTH, MOVE_WIDTH);
+        let visible = (rect.w / width) as usize;
+        let cursor = self.timeline_cursor.unwrap_or(count);
+        let first = (cursor + 1).saturating_sub(visible);
+        (width, first)
+    }
+
+    /// Moves before the timeline position under the mouse, if it's over the timeline.
+    fn timeline_index_at(&self, mx: f32, my: f32) -> Option<usize> {
+        let rect = self.timeline_rect();
+        if !rect.contains(mx, my) {
+            return None;
+        }
+        let (width, first) = self.timeline_layout();
+        // Snap to the nearest gap between moves
+        let index = first + ((mx - rect.x) / width).round() as usize;
+        Some(index.min(self.input_history.len()))
+    }
+
+    /// Left-click or drag scrubs; right-click truncates after the clicked move.
+    /// Returns true if the mouse was used.
+    pub(super) fn handle_timeline_mouse(&mut self) -> bool {
+        let (mx, my) = mouse_position();
+
+        if is_mouse_button_pressed(MouseButton::Right)
+            && let Some(index) = self.timeline_index_at(mx, my)
+        {
+            self.set_timeline_cursor(index);
+            self.truncate_at_cursor();
+            return true;
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.timeline_dragging = self.timeline_index_at(mx, my).is_some();
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.timeline_dragging = false;
+        }
+        if self.timeline_dragging {
+            let rect = self.timeline_rect();
+            let clamped_x = mx.clamp(rect.x, rect.x + rect.w - 1.0);
+            if let Some(index) = self.timeline_index_at(clamped_x, rect.y + 1.0) {
+                self.set_timeline_cursor(index);
+            }
+        }
+        self.timeline_dragging
+    }
+
+    /// Moves in text notation, with the moves after the cursor dimmed.
+    pub(super) fn render_timeline(&self) {
+        let rect = self.timeline_rect();
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::from_rgba(40, 40, 50, 255),
+        );
+
+        let (width, first) = self.timeline_layout();
+        let cursor = self.timeline_cursor.unwrap_or(self.input_history.len());
+        let row_y = rect.y + 4.0;
+        let row_h = 22.0;
+
+        for (i, action) in self.input_history.iter().enumerate().skip(first) {
+            let x = rect.x + (i - first) as f32 * width;
+            if x + width > rect.x + rect.w {
+                break;
+            }
+            let color = if i < cursor {
+                Color::from_rgba(70, 70, 90, 255)
+            } else {
+                Color::from_rgba(50, 50, 60, 255)
+            };
+            draw_rectangle(x + 1.0, row_y, width - 2.0, row_h, color);
+
+            let text = action.notation().to_string();
+            let dims = measure_text(&text, None, 18, 1.0);
+            draw_text(
+                &text,
+                x + (width - dims.width) / 2.0,
+                row_y + 16.0,
+                18.0,
+                if i < cursor { WHITE } else { GRAY },
+            );
+        }
+
+        // Cursor between moves
+        let cursor_x = rect.x + cursor.saturating_sub(first) as f32 * width;
+        draw_line(
+            cursor_x,
+            row_y - 2.0,
+            cursor_x,
+            row_y + row_h + 2.0,
+            3.0,
+            YELLOW,
+        );
+
+        let status = if self.timeline_cursor.is_some() {
+            format!(
+                "Move {}/{}: new moves insert here. [ ] step, Del truncates, right-click truncates",
+                cursor,
+                self.input_history.len()
+            )
+        } else {
+            format!("{} moves. Click or [ ] to scrub", self.input_history.len())
+        };
+        draw_text(
+            &status,
+            rect.x + 4.0,
+            rect.y + rect.h - 6.0,
+            16.0,
+            LIGHTGRAY,
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use macroquad::prelude::*;
+
+use crate::grid::Cell;
+use crate::position::Position;
+
+use super::timeline::TIMELINE_HEIGHT;
+use super::{Editor, PADDING, Rect, TOOLBAR_WIDTH, grid_wheel};
+
+/// Largest cell size zooming in can reach, in pixels.
+const MAX_CELL_SIZE: f32 = 96.0;
+/// Zoom factor per wheel notch.
+const ZOOM_STEP: f32 = 1.15;
+/// Longest side of the minimap, in pixels.
+const MINIMAP_SIZE: f32 = 160.0;
+
+/// Restrict drawing to a screen rectangle, or lift the restriction with None.
+pub(super) fn set_clip(clip: Option<Rect>) {
+    let scale = screen_dpi_scale();
+    let clip = clip.map(|r| {
+        (
+            (r.x * scale) as i32,
+            (r.y * scale) as i32,
+            (r.w * scale) as i32,
+            (r.h * scale) as i32,
+        )
+    });
+    // SAFETY: the editor only draws from the main thread
+    unsafe { get_internal_gl() }.quad_gl.scissor(clip);
+}
+
+fn minimap_color(cell: Cell) -> Color {
+    match cell {
+        Cell::Empty => Color::from_rgba(60, 60, 70, 255),
+        Cell::Wall => Color::from_rgba(150, 150, 150, 255),
+        Cell::Player(_) => GREEN,
+        Cell::Rat(_) => RED,
+        Cell::CyborgRat(_) => MAGENTA,
+        Cell::Plank => BROWN,
+        Cell::Spiderweb => LIGHTGRAY,
+        Cell::BlackHole => BLACK,
+        Cell::Explosive => ORANGE,
+        Cell::Trigger(_) => YELLOW,
+    }
+}
+
+impl Editor {
+    /// Cell size that fits the whole grid in a pane.
+    pub(super) fn fit_cell_size(&self) -> f32 {
+        let available_width = screen_width() - self.panes_x() - PADDING * 2.0;
+        let pane_width = available_width / 2.0;
+        let available_height = screen_height() - PADDING * 3.0 - TIMELINE_HEIGHT;
+
+        let cell_w = pane_width / self.initial_grid.width() as f32;
+        let cell_h = available_height / self.initial_grid.height() as f32;
+        cell_w.min(cell_h)
+    }
+
+    /// Left edge of the first pane, right of the toolbar and the stamp palette column.
+    pub(super) fn panes_x(&self) -> f32 {
+        TOOLBAR_WIDTH + PADDING + self.palette_column_width()
+    }
+
+    /// Screen rectangle of a pane.
+    pub(super) fn pane_rect(&self, pane: usize) -> Rect {
+        let (pane_width, pane_height, _) = self.pane_layout();
+        Rect {
+            x: self.panes_x() + pane as f32 * (pane_width + PADDING),
+            y: PADDING,
+            w: pane_width,
+            h: pane_height,
+        }
+    }
+
+    /// Zoom by `factor`, keeping the grid point under the screen point `(mx, my)` in place.
+    fn zoom_at(&mut self, mx: f32, my: f32, factor: f32) {
+        let fit = self.fit_cell_size();
+        let max_zoom = (MAX_CELL_SIZE / fit).max(1.0);
+        let old_zoom = self.zoom;
+        self.zoom = (self.zoom * factor).clamp(1.0, max_zoom);
+
+        // Offsets are measured from the pane center, so scale the pan around the cursor
+        let pane = self.screen_to_grid(mx, my).map_or(0, |(_, pane)| pane);
+        let rect = self.pane_rect(pane);
+        let center = vec2(rect.x + rect.w / 2.0, rect.y + rect.h / 2.0);
+        let cursor = vec2(mx, my) - center;
+        let scale = self.zoom / old_zoom;
+        self.pan = cursor - (cursor - self.pan) * scale;
+        self.clamp_pan();
+    }
+
+    /// Keep at least part of the grid in view, and center it when it fits.
+    pub(super) fn clamp_pan(&mut self) {
+        let (pane_width, pane_height, cell_size) = self.pane_layout();
+        let grid_w = self.initial_grid.width() as f32 * cell_size;
+        let grid_h = self.initial_grid.height() as f32 * cell_size;
+        let max_x = ((grid_w - pane_width) / 2.0).max(0.0);
+        let max_y = ((grid_h - pane_height) / 2.0).max(0.0);
+        self.pan = vec2(
+            self.pan.x.clamp(-max_x, max_x),
+            self.pan.y.clamp(-max_y, max_y),
+        );
+    }
+
+    pub(super) fn reset_view(&mut self) {
+        self.zoom = 1.0;
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Wheel zoom, middle-drag pan and minimap clicks. Returns true if the mouse was used.
+    ///
+    /// The Trigger and Player tools use the plain wheel themselves, so they zoom with Ctrl+wheel.
+    pub(super) fn handle_view_input(&mut self) -> bool {
+        let (mx, my) = mouse_position();
+        self.clamp_pan();
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+
+        let scroll_y = grid_wheel();
+        if scroll_y != 0.0 && (ctrl || !self.tool.uses_wheel()) {
+            let factor = if scroll_y > 0.0 {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            self.zoom_at(mx, my, factor);
+        }
+
+        if is_mouse_button_down(MouseButton::Middle) {
+            if let Some((last_x, last_y)) = self.pan_anchor {
+                self.pan += vec2(mx - last_x, my - last_y);
+                self.clamp_pan();
+            }
+            self.pan_anchor = Some((mx, my));
+        } else {
+            self.pan_anchor = None;
+        }
+
+        // Clicking or dragging on the minimap centers the view there
+        if is_mouse_button_down(MouseButton::Left)
+            && let Some(minimap) = self.minimap_rect()
+            && minimap.contains(mx, my)
+        {
+            if is_mouse_button_pressed(MouseButton::Left) || self.minimap_dragging {
+                self.minimap_dragging = true;
+                let (_, _, cell_size) = self.pane_layout();
+                let scale = minimap.w / self.initial_grid.width() as f32;
+                let grid_x = (mx - minimap.x) / scale;
+                let grid_y = (my - minimap.y) / scale;
+                let grid_w = self.initial_grid.width() as f32;
+                let grid_h = self.initial_grid.height() as f32;
+                self.pan = vec2(
+                    (grid_w / 2.0 - grid_x) * cell_size,
+                    (grid_h / 2.0 - grid_y) * cell_size,
+                );
+                self.clamp_pan();
+            }
+            return self.minimap_dragging;
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.minimap_dragging = false;
+        }
+        self.minimap_dragging
+    }
+
+    /// Minimap in the bottom-right corner of the left pane, shown while zoomed past the fit.
+    fn minimap_rect(&self) -> Option<Rect> {
+        if self.zoom <= 1.0 {
+            return None;
+        }
+        let width = self.initial_grid.width() as f32;
+        let height = self.initial_grid.height() as f32;
+        let scale = MINIMAP_SIZE / width.max(height);
+        let pane = self.pane_rect(0);
+        let (w, h) = (width * scale, height * scale);
+        Some(Rect {
+            x: pane.x + pane.w - w - 8.0,
+            y: pane.y + pane.h - h - 8.0,
+            w,
+            h,
+        })
+    }
+
+    pub(super) fn render_minimap(&self) {
+        let Some(minimap) = self.minimap_rect() else {
+            return;
+        };
+        let scale = minimap.w / self.initial_grid.width() as f32;
+
+        draw_rectangle(
+            minimap.x - 2.0,
+            minimap.y - 2.0,
+            minimap.w + 4.0,
+            minimap.h + 4.0,
+            Color::from_rgba(20, 20, 30, 230),
+        );
+        for (pos, cell) in self.initial_grid.entries() {
+            draw_rectangle(
+                minimap.x + pos.x as f32 * scale,
+                minimap.y + pos.y as f32 * scale,
+                scale.max(1.0),
+                scale.max(1.0),
+                minimap_color(cell),
+            );
+        }
+
+        // Visible part of the grid
+        let pane = self.pane_rect(0);
+        let (_, _, cell_size) = self.pane_layout();
+        let (offset_x, offset_y) = self.grid_offset(0);
+        let view_x = ((pane.x - offset_x) / cell_size).max(0.0);
+        let view_y = ((pane.y - offset_y) / cell_size).max(0.0);
+        let view_w = (pane.w / cell_size).min(self.initial_grid.width() as f32 - view_x);
+        let view_h = (pane.h / cell_size).min(self.initial_grid.height() as f32 - view_y);
+        draw_rectangle_lines(
+            minimap.x + view_x * scale,
+            minimap.y + view_y * scale,
+            view_w * scale,
+            view_h * scale,
+            2.0,
+            WHITE,
+        );
+    }
+
+    /// Returns true if the cell at `pos` is at least partly visible in `pane`.
+    pub(super) fn cell_visible(&self, pos: Position, pane: usize) -> bool {
+        let (_, _, cell_size) = self.pane_layout();
+        let (x, y) = self.grid_to_screen(pos, pane);
+        let rect = self.pane_rect(pane);
+        x + cell_size > rect.x
+            && x < rect.x + rect.w
+            && y + cell_size > rect.y
+            && y < rect.y + rect.h
+    }
+}
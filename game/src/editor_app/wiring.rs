@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::read_to_string;
+
+use macroquad::prelude::*;
+
+use crate::game::{ZapPreview, preview_zap};
+use crate::grid::{Cell, Grid, LevelMetadata};
+use crate::position::Position;
+use crate::storage::last_used_completed_levels;
+
+use super::{Editor, level_json_path};
+
+/// Trigger connections, zap previews and portal targets for the initial grid.
+pub(super) struct Wiring {
+    /// Cells and portals the overlay was computed for, to detect when it's stale.
+    cells: Vec<Vec<Cell>>,
+    portal_targets: Vec<(Position, String)>,
+    /// Trigger positions and what zapping them does, by digit.
+    triggers: BTreeMap<u8, (Vec<Position>, ZapPreview)>,
+    /// Portal positions with their label and whether the target is completed.
+    portals: Vec<(Position, String, bool)>,
+}
+
+/// Portals sorted by position, for comparing grids.
+fn portal_targets(grid: &Grid) -> Vec<(Position, String)> {
+    let mut portals: Vec<_> = grid
+        .portals()
+        .map(|(pos, level)| (pos, level.to_string()))
+        .collect();
+    portals.sort_by_key(|&(pos, _)| (pos.y, pos.x));
+    portals
+}
+
+impl Wiring {
+    fn new(grid: &Grid, completed: &HashSet<String>) -> Self {
+        let mut positions: BTreeMap<u8, Vec<Position>> = BTreeMap::new();
+        for (pos, cell) in grid.entries() {
+            if let Cell::Trigger(n) = cell {
+                positions.entry(n).or_default().push(pos);
+            }
+        }
+        let triggers = positions
+            .into_iter()
+            .map(|(n, positions)| (n, (positions, preview_zap(grid, n))))
+            .collect();
+
+        let portal_targets = portal_targets(grid);
+        let portals = portal_targets
+            .iter()
+            .map(|(pos, level)| {
+                // Fall back to the level path when the target can't be read
+                let name = read_to_string(level_json_path(level))
+                    .ok()
+                    .and_then(|json| LevelMetadata::try_parse(&json).ok())
+                    .map_or_else(|| level.clone(), |metadata| metadata.name);
+                (*pos, name, completed.contains(level))
+            })
+            .collect();
+
+        Self {
+            cells: grid.cells().to_vec(),
+            portal_targets,
+            triggers,
+            portals,
+        }
+    }
+}
+
+/// Distinct color for each trigger digit.
+fn digit_color(n: u8) -> Color {
+    const COLORS: [Color; 9] = [
+        YELLOW,
+        SKYBLUE,
+        PINK,
+        LIME,
+        ORANGE,
+        VIOLET,
+        GOLD,
+        Color::new(0.3, 1.0, 0.9, 1.0),
+        BEIGE,
+    ];
+    COLORS[(n as usize).saturating_sub(1) % COLORS.len()]
+}
+
+impl Editor {
+    pub(super) fn toggle_wiring(&mut self) {
+        self.wiring_enabled = !self.wiring_enabled;
+        self.wiring = None;
+        // Completion may have changed while the overlay was off. Show the last profile played.
+        self.completed_levels = last_used_completed_levels();
+    }
+
+    /// Recompute the overlay if the initial grid changed.
+    pub(super) fn update_wiring(&mut self) {
+        if !self.wiring_enabled {
+            return;
+        }
+        let grid = &self.initial_grid;
+        if !self.wiring.as_ref().is_some_and(|w| {
+            w.cells.as_slice() == grid.cells() && w.portal_targets == portal_targets(grid)
+        }) {
+            self.wiring = Some(Wiring::new(grid, &self.completed_levels));
+        }
+    }
+
+    /// Trigger links, zap previews and portal labels on the left pane.
+    /// Hovering a trigger shows only its digit's wiring.
+    pub(super) fn render_wiring(&self, cell_size: f32) {
+        let Some(wiring) = &self.wiring else {
+            return;
+        };
+
+        let (mx, my) = mouse_position();
+        let hovered = match self.screen_to_grid(mx, my) {
+            Some((pos, 0)) => match self.initial_grid.at(pos) {
+                Cell::Trigger(n) => Some(n),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let half = cell_size / 2.0;
+        let center = |pos: Position| {
+            let (x, y) = self.grid_to_screen(pos, 0);
+            (x + half, y + half)
+        };
+
+        for (&n, (positions, preview)) in &wiring.triggers {
+            if hovered.is_some_and(|h| h != n) {
+                continue;
+            }
+            let color = digit_color(n);
+
+            // Every trigger zaps all the others with its digit
+            for (i, &a) in positions.iter().enumerate() {
+                for &b in &positions[i + 1..] {
+                    let (ax, ay) = center(a);
+                    let (bx, by) = center(b);
+                    draw_line(ax, ay, bx, by, 2.0, Color { a: 0.7, ..color });
+                }
+            }
+
+            // Walls the zap would create
+            for &pos in &preview.walls {
+                let (x, y) = self.grid_to_screen(pos, 0);
+                draw_rectangle(x, y, cell_size, cell_size, Color { a: 0.2, ..color });
+                draw_rectangle_lines(
+                    x + 2.0,
+                    y + 2.0,
+                    cell_size - 4.0,
+                    cell_size - 4.0,
+                    2.0,
+                    color,
+                );
+            }
+
+            // Explosives it would set off, directly or by chaining
+            for &pos in &preview.explosions {
+                let (x, y) = center(pos);
+                draw_circle_lines(x, y, half * 0.8, 3.0, color);
+            }
+        }
+
+        // Portal targets, green once completed
+        for (pos, name, completed) in &wiring.portals {
+            let (x, y) = self.grid_to_screen(*pos, 0);
+            let label = if *completed {
+                format!("{} (solved)", name)
+            } else {
+                name.clone()
+            };
+            let dims = measure_text(&label, None, 16, 1.0);
+            let label_x = x + (cell_size - dims.width) / 2.0;
+            let label_y = y - 4.0;
+            draw_rectangle(
+                label_x - 3.0,
+                label_y - dims.offset_y - 2.0,
+                dims.width + 6.0,
+                dims.height + 4.0,
+                Color::from_rgba(20, 20, 30, 200),
+            );
+            draw_text(
+                &label,
+                label_x,
+                label_y,
+                16.0,
+                if *completed { GREEN } else { WHITE },
+            );
+        }
+    }
+}
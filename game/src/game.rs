@@ -1,21 +1,70 @@
 use std::borrow::BorrowMut;
 use std::collections::HashSet;
 
+use serde::{Deserialize, Serialize};
+
 use crate::direction::Dir4;
 use crate::grid::{Cell, Grid};
 use crate::levels;
 use crate::position::Position;
-use crate::storage::strip_path_prefix;
 
 mod animation;
 mod cyborg_distance;
 mod cyborg_rat;
 mod explosion;
+mod history;
 mod player;
 mod rat;
+mod solver;
+mod trace;
 mod zap;
 
-const MOVE_SPEED: f32 = 15.0;
+pub(crate) use cyborg_distance::CyborgDistance;
+pub(crate) use cyborg_rat::cyborg_distances;
+pub(crate) use history::HistoryTree;
+pub(crate) use solver::{SOLUTION_COUNT_CAP, Solver, SolverStatus};
+pub(crate) use trace::{Phase, Rejection, TraceStep, trace_action};
+pub(crate) use zap::{ZapPreview, preview_zap};
+
+/// Animation steps per second at normal speed.
+const BASE_SPEED: f32 = 15.0;
+
+/// How fast resolved moves are played out on screen.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AnimationSpeed {
+    /// Multiplier for entities moving between cells.
+    pub(crate) moves: f32,
+    /// Multiplier for zaps and explosions.
+    pub(crate) effects: f32,
+    /// Show the result of each move straight away, without animating it.
+    pub(crate) instant: bool,
+}
+
+/// Animation speed multipliers to choose from in the settings.
+pub(crate) const SPEEDS: [f32; 6] = [0.5, 0.75, 1.0, 1.5, 2.0, 3.0];
+
+impl AnimationSpeed {
+    /// Speeds limited to the range of `SPEEDS`, since a speed of zero never finishes animating.
+    pub(crate) fn clamped(self) -> Self {
+        let (min, max) = (SPEEDS[0], SPEEDS[SPEEDS.len() - 1]);
+        Self {
+            moves: self.moves.clamp(min, max),
+            effects: self.effects.clamp(min, max),
+            ..self
+        }
+    }
+}
+
+impl Default for AnimationSpeed {
+    fn default() -> Self {
+        Self {
+            moves: 1.0,
+            effects: 1.0,
+            instant: false,
+        }
+    }
+}
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum PlayState {
@@ -44,12 +93,48 @@ pub(crate) struct Zapping {
     pub(crate) progress: f32,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum Action {
     Move(Dir4),
     Stall,
 }
 
+impl Action {
+    /// Single-character notation: `N`/`S`/`E`/`W` for moves and `.` for stalling.
+    pub(crate) fn notation(self) -> char {
+        match self {
+            Action::Move(Dir4::North) => 'N',
+            Action::Move(Dir4::South) => 'S',
+            Action::Move(Dir4::East) => 'E',
+            Action::Move(Dir4::West) => 'W',
+            Action::Stall => '.',
+        }
+    }
+
+    pub(crate) fn from_notation(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'N' => Some(Action::Move(Dir4::North)),
+            'S' => Some(Action::Move(Dir4::South)),
+            'E' => Some(Action::Move(Dir4::East)),
+            'W' => Some(Action::Move(Dir4::West)),
+            '.' => Some(Action::Stall),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn format_moves(moves: &[Action]) -> String {
+        moves.iter().map(|m| m.notation()).collect()
+    }
+
+    /// Parse a move sequence, ignoring whitespace. Returns None on any unknown character.
+    pub(crate) fn parse_moves(text: &str) -> Option<Vec<Action>> {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(Action::from_notation)
+            .collect()
+    }
+}
+
 /// Handles move resolution and animation.
 /// Used for both instant resolution and animated playback.
 #[derive(Clone)]
@@ -66,6 +151,8 @@ pub(crate) struct MoveHandler<G = Grid> {
     pub(crate) exploding: Vec<Exploding>,
     /// Explosions queued for the next wave.
     pub(crate) pending_explosions: Vec<Position>,
+    /// Phases resolved so far, when debugging a move.
+    pub(crate) trace: Option<Vec<TraceStep>>,
 }
 
 impl<G: BorrowMut<Grid>> MoveHandler<G> {
@@ -77,6 +164,7 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
             triggered_numbers: Vec::new(),
             exploding: Vec::new(),
             pending_explosions: Vec::new(),
+            trace: None,
         }
     }
 
@@ -107,11 +195,30 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 pub(crate) struct GameState {
     pub(crate) grid: Grid,
     pub(crate) initial_grid: Grid,
+    /// States from the initial grid to the current one, along `node`'s path in `tree`.
     pub(crate) history: Vec<Grid>,
+    /// Moves that led to each state in `history` after the first.
+    pub(crate) actions: Vec<Action>,
+    /// Every position reached in this level, including lines that were undone.
+    pub(crate) tree: HistoryTree,
+    /// Current position in `tree`.
+    pub(crate) node: usize,
+    /// Positions left by restarting, most recent last.
+    pub(crate) restarted: Vec<usize>,
+    /// Steps taken back by undo, most recent last. Cleared by any new move.
+    pub(crate) redo: Vec<Undone>,
     pub(crate) queued_move: Option<Action>,
     pub(crate) completed_levels: HashSet<String>,
 }
 
+/// A step taken back by `Game::undo`, which `Game::redo` can take again.
+#[derive(Clone)]
+pub(crate) enum Undone {
+    /// A move, by the position in the history tree it led to.
+    Move(usize),
+    Restart,
+}
+
 /// Game wrapper combining state and move handling.
 #[derive(Clone)]
 pub(crate) struct Game {
@@ -126,12 +233,40 @@ impl GameState {
         Self {
             initial_grid: grid.clone(),
             grid: grid.clone(),
-            history: vec![grid],
+            history: vec![grid.clone()],
+            actions: Vec::new(),
+            tree: HistoryTree::new(grid),
+            node: HistoryTree::ROOT,
+            restarted: Vec::new(),
+            redo: Vec::new(),
             queued_move: None,
             completed_levels,
         }
     }
 
+    pub(crate) fn can_undo(&self) -> bool {
+        self.history.len() > 1 || !self.restarted.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Move to a position in the history tree, rebuilding `history` and `actions` along its path.
+    fn set_node(&mut self, node: usize) {
+        let path = self.tree.path(node);
+        self.history = path
+            .iter()
+            .map(|&id| self.tree.node(id).grid.clone())
+            .collect();
+        self.actions = path
+            .iter()
+            .filter_map(|&id| self.tree.node(id).action)
+            .collect();
+        self.grid = self.tree.node(node).grid.clone();
+        self.node = node;
+    }
+
     /// Returns the portal destination if the player is currently standing on a portal.
     pub(crate) fn standing_on_portal(&self) -> Option<&str> {
         let (player_pos, _) = self.find_player()?;
@@ -145,19 +280,36 @@ impl GameState {
     }
 
     fn is_level_completed(&self, level: &str) -> bool {
-        self.completed_levels.contains(strip_path_prefix(level))
+        self.completed_levels.contains(level)
     }
 
     pub(crate) fn mark_level_completed(&mut self, level: &str) {
-        self.completed_levels
-            .insert(strip_path_prefix(level).to_string());
+        self.completed_levels.insert(level.to_string());
+    }
+
+    /// Levels that still need to be completed before the portal at `pos` opens.
+    pub(crate) fn missing_requirements(&self, pos: Position) -> Vec<&str> {
+        self.grid
+            .portal_requires(pos)
+            .iter()
+            .map(String::as_str)
+            .filter(|level| !self.is_level_completed(level))
+            .collect()
+    }
+
+    /// Returns the missing prerequisites if the player is standing on a locked portal.
+    pub(crate) fn standing_on_locked_portal(&self) -> Option<Vec<&str>> {
+        let (player_pos, _) = self.find_player()?;
+        self.grid.get_portal(player_pos)?;
+        let missing = self.missing_requirements(player_pos);
+        (!missing.is_empty()).then_some(missing)
     }
 
     /// Returns the display name of the portal if standing on a completed portal.
-    pub(crate) fn standing_on_completed_portal(&self) -> Option<&str> {
+    pub(crate) fn standing_on_completed_portal(&self) -> Option<String> {
         let portal = self.standing_on_portal()?;
         self.is_level_completed(portal)
-            .then(|| levels::get_level(portal).map(|l| l.display_name.as_str()))?
+            .then(|| levels::get_level(portal).map(|l| l.display_name.clone()))?
     }
 
     /// Returns the portal destination if the player just stepped onto an unvisited portal (auto-enter).
@@ -166,8 +318,10 @@ impl GameState {
         let (player_pos, _) = self.find_player()?;
         let current_portal = self.grid.get_portal(player_pos)?;
 
-        // Don't auto-enter if already completed
-        if self.is_level_completed(current_portal) {
+        // Don't auto-enter if already completed or still locked
+        if self.is_level_completed(current_portal)
+            || !self.missing_requirements(player_pos).is_empty()
+        {
             return None;
         }
 
@@ -199,7 +353,12 @@ impl GameState {
 
     /// Compute play state from grid: GameOver if no player, Won if no rats (and started with rats).
     pub(crate) fn play_state(&self) -> PlayState {
-        let play_state = self.grid.play_state();
+        self.play_state_of(&self.grid)
+    }
+
+    /// Play state of any grid reached in this level, such as one in the history tree.
+    pub(crate) fn play_state_of(&self, grid: &Grid) -> PlayState {
+        let play_state = grid.play_state();
         if play_state == PlayState::GameOver {
             return PlayState::GameOver;
         }
@@ -223,31 +382,77 @@ impl Game {
         self.state.is_level_completed(level)
     }
 
+    /// Go back to the initial grid. The attempt is kept so undo can return to it.
     pub(crate) fn restart(&mut self) {
-        self.state.grid = self.state.initial_grid.clone();
-        self.state.history = vec![self.state.grid.clone()];
+        if self.state.history.len() > 1 {
+            self.start_over();
+            self.state.redo.clear();
+        }
+    }
+
+    fn start_over(&mut self) {
+        self.state.restarted.push(self.state.node);
+        self.state.set_node(HistoryTree::ROOT);
         self.animation = None;
         self.state.queued_move = None;
     }
 
+    /// Take back the last move, or the restart that began this attempt.
     pub(crate) fn undo(&mut self) {
-        if self.state.history.len() > 1 {
-            self.state.history.pop();
-            self.state.grid = self.state.history.last().unwrap().clone();
-            self.animation = None;
-            self.state.queued_move = None;
+        let state = &mut self.state;
+        if let Some(parent) = state.tree.node(state.node).parent {
+            state.history.pop();
+            state.actions.pop();
+            state.grid = state.history.last().unwrap().clone();
+            state.redo.push(Undone::Move(state.node));
+            state.node = parent;
+        } else if let Some(node) = state.restarted.pop() {
+            state.set_node(node);
+            state.redo.push(Undone::Restart);
+        } else {
+            return;
+        }
+        self.animation = None;
+        state.queued_move = None;
+    }
+
+    /// Take again the last step undone since the last move.
+    pub(crate) fn redo(&mut self) {
+        match self.state.redo.pop() {
+            Some(Undone::Move(node)) => {
+                let state = &mut self.state;
+                let next = state.tree.node(node);
+                state.grid = next.grid.clone();
+                state.history.push(next.grid.clone());
+                state.actions.extend(next.action);
+                state.node = node;
+                self.animation = None;
+                state.queued_move = None;
+            }
+            Some(Undone::Restart) => self.start_over(),
+            None => {}
         }
     }
 
+    /// Jump to any position in the history tree. Like a new move, this clears redo.
+    pub(crate) fn go_to(&mut self, node: usize) {
+        if node != self.state.node {
+            self.state.set_node(node);
+            self.state.redo.clear();
+        }
+        self.animation = None;
+        self.state.queued_move = None;
+    }
+
     pub(crate) fn is_animating(&self) -> bool {
         self.animation.is_some()
     }
 
-    pub(crate) fn begin_action(&mut self, m: Action) {
+    pub(crate) fn begin_action(&mut self, m: Action, speed: AnimationSpeed) {
         let prev_grid = self.state.grid.clone();
 
         // Handler #1: resolve instantly
-        if !self.apply_action(m) {
+        if !self.apply_action(m) || speed.instant {
             return;
         }
 
@@ -260,13 +465,13 @@ impl Game {
         }
     }
 
-    pub(crate) fn try_begin_action(&mut self, m: Action) {
+    pub(crate) fn try_begin_action(&mut self, m: Action, speed: AnimationSpeed) {
         if self.is_animating() {
             if self.state.queued_move.is_none() {
                 self.state.queued_move = Some(m);
             }
         } else {
-            self.begin_action(m);
+            self.begin_action(m, speed);
         }
     }
 
@@ -283,6 +488,9 @@ impl Game {
         resolver.resolve_all();
 
         self.state.history.push(self.state.grid.clone());
+        self.state.actions.push(m);
+        self.state.node = self.state.tree.child(self.state.node, m, &self.state.grid);
+        self.state.redo.clear();
 
         true
     }
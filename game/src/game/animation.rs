@@ -2,7 +2,7 @@ use std::borrow::BorrowMut;
 
 use crate::grid::{Cell, Grid};
 
-use super::{Game, MOVE_SPEED, MoveHandler};
+use super::{AnimationSpeed, BASE_SPEED, Game, MoveHandler, Phase};
 
 impl<G: BorrowMut<Grid>> MoveHandler<G> {
     /// Resolve all pending animations immediately.
@@ -16,19 +16,22 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
     /// eliminating bugs where the two could diverge.
     pub(crate) fn resolve_all(&mut self) {
         let mut dt = f32::INFINITY;
-        let completed = self.advance_animation(&mut dt);
+        let completed = self.advance_animation(&mut dt, AnimationSpeed::default());
         assert!(completed, "resolve_all should complete all animations");
     }
 
     /// Advance animation by dt seconds. Returns true if animation is complete.
-    pub(crate) fn advance_animation(&mut self, dt: &mut f32) -> bool {
+    pub(crate) fn advance_animation(&mut self, dt: &mut f32, speed: AnimationSpeed) -> bool {
+        let move_speed = BASE_SPEED * speed.moves;
+        let effect_speed = BASE_SPEED * speed.effects;
+
         // First, handle movement animations
         if !self.moving.is_empty() {
             let mut all_done = true;
             let mut max_advancement: f32 = 0.0;
             for m in &mut self.moving {
                 max_advancement = max_advancement.max(1.0 - m.progress);
-                m.progress = (m.progress + *dt * MOVE_SPEED).min(1.0);
+                m.progress = (m.progress + *dt * move_speed).min(1.0);
                 if m.progress < 1.0 {
                     all_done = false;
                 }
@@ -36,7 +39,8 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 
             if all_done {
                 self.finish_moving();
-                *dt -= max_advancement / MOVE_SPEED;
+                self.record(Phase::FinishMoving, Vec::new(), None);
+                *dt -= max_advancement / move_speed;
             } else {
                 return false;
             }
@@ -45,6 +49,7 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
         // If we have pending zaps but no moves are animating, start them
         if !self.triggered_numbers.is_empty() {
             self.start_zap_wave();
+            self.record(Phase::StartZapWave, Vec::new(), None);
         }
 
         // Then, handle zap animations
@@ -53,7 +58,7 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
             let mut max_advancement: f32 = 0.0;
             for z in &mut self.zapping {
                 max_advancement = max_advancement.max(1.0 - z.progress);
-                z.progress = (z.progress + *dt * MOVE_SPEED).min(1.0);
+                z.progress = (z.progress + *dt * effect_speed).min(1.0);
                 if z.progress < 1.0 {
                     all_done = false;
                 }
@@ -61,7 +66,8 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 
             if all_done {
                 self.finish_zap_wave();
-                *dt -= max_advancement / MOVE_SPEED;
+                self.record(Phase::FinishZapWave, Vec::new(), None);
+                *dt -= max_advancement / effect_speed;
             } else {
                 return false;
             }
@@ -71,6 +77,7 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
             // If we have pending explosions but nothing is animating, start the wave
             if self.exploding.is_empty() {
                 self.start_explosion_wave();
+                self.record(Phase::StartExplosionWave, Vec::new(), None);
             }
 
             // Then, handle explosion animations
@@ -78,7 +85,7 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
             let mut max_advancement: f32 = 0.0;
             for e in &mut self.exploding {
                 max_advancement = max_advancement.max(1.0 - e.progress);
-                e.progress = (e.progress + *dt * MOVE_SPEED).min(1.0);
+                e.progress = (e.progress + *dt * effect_speed).min(1.0);
                 if e.progress < 1.0 {
                     all_done = false;
                 }
@@ -86,7 +93,8 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 
             if all_done {
                 self.finish_explosion_wave();
-                *dt -= max_advancement / MOVE_SPEED;
+                self.record(Phase::FinishExplosionWave, Vec::new(), None);
+                *dt -= max_advancement / effect_speed;
             } else {
                 return false;
             }
@@ -126,9 +134,9 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 }
 
 impl Game {
-    pub(crate) fn animate(&mut self, mut dt: f32) {
+    pub(crate) fn animate(&mut self, mut dt: f32, speed: AnimationSpeed) {
         while let Some(ref mut handler) = self.animation {
-            let done = handler.advance_animation(&mut dt);
+            let done = handler.advance_animation(&mut dt, speed);
             if !done {
                 break;
             }
@@ -139,7 +147,7 @@ impl Game {
                 // to allow portal transition to happen
                 self.state.queued_move = None;
             } else if let Some(dir) = self.state.queued_move.take() {
-                self.begin_action(dir);
+                self.begin_action(dir, speed);
             }
         }
     }
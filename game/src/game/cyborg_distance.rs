@@ -1,4 +1,4 @@
-use std::{cmp::Ordering, ops::Sub};
+use std::{cmp::Ordering, fmt, ops::Sub};
 
 use crate::{direction::Dir8, position::Position};
 
@@ -11,6 +11,12 @@ impl CyborgDistance {
     pub(crate) const ZERO: CyborgDistance = CyborgDistance(0, 0);
     pub(crate) const ONE_ORTHO: CyborgDistance = CyborgDistance(1, 0);
 
+    /// Euclidean path length, A + B*sqrt(2).
+    pub(crate) fn length(self) -> f32 {
+        let CyborgDistance(ortho, diag) = self;
+        ortho as f32 + diag as f32 * std::f32::consts::SQRT_2
+    }
+
     pub(crate) fn add_step(self, dir: Dir8) -> CyborgDistance {
         let CyborgDistance(ortho, diag) = self;
         if dir.is_diagonal() {
@@ -26,6 +32,13 @@ impl CyborgDistance {
     }
 }
 
+impl fmt::Display for CyborgDistance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let CyborgDistance(ortho, diag) = *self;
+        write!(f, "({}, {})", ortho, diag)
+    }
+}
+
 impl Ord for CyborgDistance {
     fn cmp(&self, other: &Self) -> Ordering {
         (*self - *other).compare_with_zero()
@@ -7,7 +7,16 @@ use crate::grid::{Cell, Grid};
 use crate::position::Position;
 
 use super::cyborg_distance::{CyborgDistance, DijkstraEntry};
-use super::{MoveHandler, Moving};
+use super::{MoveHandler, Moving, Phase, Rejection};
+
+/// Cyborg rat distances from the player to every cell a cyborg rat could path through.
+pub(crate) fn cyborg_distances(grid: &Grid) -> HashMap<Position, CyborgDistance> {
+    let handler = MoveHandler::new(grid.clone());
+    match handler.find_player() {
+        Some((player, _)) => handler.compute_cyborg_distances(player),
+        None => HashMap::new(),
+    }
+}
 
 impl<G: BorrowMut<Grid>> MoveHandler<G> {
     /// Compute shortest path distances from target using Dijkstra with A + B*sqrt(2) metric
@@ -90,6 +99,11 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
                     to: cyborg_pos,
                 });
             }
+            let phase = Phase::CyborgRat {
+                pos: cyborg_pos,
+                distance: None,
+            };
+            self.record(phase, Vec::new(), None);
         }
 
         // Sort reachable cyborg rats by distance (closest first), tiebreak by position
@@ -102,28 +116,41 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
         for (current_dist, cyborg_pos) in movable_cyborgs {
             // Find best adjacent cell
             let mut best_move: Option<(Dir8, CyborgDistance)> = None;
+            // Candidates are only collected for the trace
+            let tracing = self.trace.is_some();
+            let mut rejected = Vec::new();
+            let mut valid = Vec::new();
+            let mut reject = |dir, rejection| {
+                if tracing {
+                    rejected.push((dir, rejection));
+                }
+            };
 
             for dir in Dir8::all() {
                 let new_pos = cyborg_pos + dir.delta();
 
                 let Some(&target_dist) = distances.get(&new_pos) else {
+                    reject(dir, Rejection::NoPath);
                     continue;
                 };
 
                 // Must be an improvement (to allow moving toward goal)
                 // unless the player is about to kill us
                 if target_dist >= current_dist && current_dist != CyborgDistance::ONE_ORTHO {
+                    reject(dir, Rejection::NotCloser);
                     continue;
                 }
 
                 // Don't move to orthogonally adjacent to player where they can reach us
                 // (distance (1, 0))
                 if target_dist == CyborgDistance::ONE_ORTHO {
+                    reject(dir, Rejection::NextToPlayer);
                     continue;
                 }
 
                 // Can't attack from in front of player (sword blocks)
                 if new_pos == player && dir == blocked_dir {
+                    reject(dir, Rejection::Sword);
                     continue;
                 }
 
@@ -132,16 +159,27 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 
                 // Can't move into walls, other cyborg rats, spiderwebs, black holes
                 if target_cell.blocks_cyborg_rat() {
+                    reject(dir, Rejection::Blocked(target_cell));
                     continue;
                 }
 
                 // This is a valid move - check if it's the best
+                if tracing {
+                    valid.push(dir);
+                }
                 if best_move.is_none_or(|(_, best_dist)| target_dist < best_dist) {
                     best_move = Some((dir, target_dist));
                 }
             }
-
-            if let Some((dir, _)) = best_move {
+            let chosen_dir = best_move.map(|(dir, _)| dir);
+            rejected.extend(
+                valid
+                    .into_iter()
+                    .filter(|&dir| Some(dir) != chosen_dir)
+                    .map(|dir| (dir, Rejection::NotBest)),
+            );
+
+            if let Some(dir) = chosen_dir {
                 self.begin_move(Moving {
                     cell: Cell::CyborgRat(dir),
                     from: cyborg_pos,
@@ -157,6 +195,12 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
                     to: cyborg_pos,
                 });
             }
+
+            let phase = Phase::CyborgRat {
+                pos: cyborg_pos,
+                distance: Some(current_dist),
+            };
+            self.record(phase, rejected, chosen_dir);
         }
     }
 }
@@ -0,0 +1,72 @@
+use crate::grid::Grid;
+
+use super::Action;
+
+/// Every position reached in a level, as a tree of moves from the initial grid.
+/// Undoing and playing a different move starts a new branch instead of losing the old one.
+#[derive(Clone)]
+pub(crate) struct HistoryTree {
+    nodes: Vec<HistoryNode>,
+}
+
+#[derive(Clone)]
+pub(crate) struct HistoryNode {
+    pub(crate) grid: Grid,
+    pub(crate) parent: Option<usize>,
+    /// Move from the parent to this position, None for the root.
+    pub(crate) action: Option<Action>,
+    /// Moves tried from this position, in the order they were first played.
+    pub(crate) children: Vec<usize>,
+}
+
+impl HistoryTree {
+    pub(crate) const ROOT: usize = 0;
+
+    pub(crate) fn new(grid: Grid) -> Self {
+        Self {
+            nodes: vec![HistoryNode {
+                grid,
+                parent: None,
+                action: None,
+                children: Vec::new(),
+            }],
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn node(&self, id: usize) -> &HistoryNode {
+        &self.nodes[id]
+    }
+
+    /// The position `action` leads to from `parent`, added if that move wasn't tried before.
+    /// Moves are deterministic, so a move tried again always reaches the same grid.
+    pub(crate) fn child(&mut self, parent: usize, action: Action, grid: &Grid) -> usize {
+        if let Some(&id) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|&&id| self.nodes[id].action == Some(action))
+        {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(HistoryNode {
+            grid: grid.clone(),
+            parent: Some(parent),
+            action: Some(action),
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    /// Node ids from the root to `id`.
+    pub(crate) fn path(&self, id: usize) -> Vec<usize> {
+        let mut path: Vec<usize> =
+            std::iter::successors(Some(id), |&id| self.nodes[id].parent).collect();
+        path.reverse();
+        path
+    }
+}
@@ -4,7 +4,7 @@ use crate::grid::Cell;
 use crate::position::Position;
 use crate::{direction::Dir4, grid::Grid};
 
-use super::{Action, Game, GameState, MoveHandler, Moving};
+use super::{Action, Game, GameState, MoveHandler, Moving, Phase};
 
 impl<G: BorrowMut<Grid>> MoveHandler<G> {
     pub(crate) fn find_player(&self) -> Option<(Position, Dir4)> {
@@ -46,6 +46,7 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
                 new_dir = current_dir;
             }
         }
+        self.record(Phase::PlayerMove(m), Vec::new(), None);
 
         self.move_cyborg_rats(new_pos, new_dir);
         self.move_rats(new_pos, new_dir);
@@ -77,6 +78,9 @@ impl GameState {
 
 impl Game {
     pub(crate) fn enter_portal(&self) -> Option<&str> {
+        if self.state.standing_on_locked_portal().is_some() {
+            return None;
+        }
         self.state.standing_on_portal()
     }
 }
@@ -4,7 +4,7 @@ use crate::direction::{Dir4, Dir8};
 use crate::grid::{Cell, Grid};
 use crate::position::Position;
 
-use super::{MoveHandler, Moving};
+use super::{MoveHandler, Moving, Phase, Rejection};
 
 impl<G: BorrowMut<Grid>> MoveHandler<G> {
     pub(crate) fn move_rats(&mut self, player: Position, player_facing: Dir4) {
@@ -50,6 +50,14 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
 
             // Try each move in order
             let mut chosen_dir: Option<Dir8> = None;
+            // Rejected moves are only collected for the trace
+            let tracing = self.trace.is_some();
+            let mut rejected = Vec::new();
+            let mut reject = |dir, rejection| {
+                if tracing {
+                    rejected.push((dir, rejection));
+                }
+            };
             for dir in moves_to_try {
                 let new_pos = rat_pos + dir.delta();
 
@@ -57,11 +65,13 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
                 // If rat is moving to player's destination, skip block check
                 // (player is clearing whatever was there, e.g. spiderweb)
                 if target_cell.blocks_rat() {
+                    reject(dir, Rejection::Blocked(target_cell));
                     continue;
                 }
 
                 // Can't attack player from in front (sword blocks)
                 if new_pos == player && dir == blocked_dir {
+                    reject(dir, Rejection::Sword);
                     continue;
                 }
 
@@ -85,6 +95,12 @@ impl<G: BorrowMut<Grid>> MoveHandler<G> {
                     to: rat_pos,
                 });
             }
+
+            let phase = Phase::Rat {
+                pos: rat_pos,
+                dist_sq: rat_pos.dist_sq(player),
+            };
+            self.record(phase, rejected, chosen_dir);
         }
     }
 }
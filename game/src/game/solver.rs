@@ -0,0 +1,256 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::direction::Dir4;
+use crate::grid::{Cell, Grid};
+
+use super::{Action, MoveHandler, PlayState};
+
+/// Stop searching after this many distinct states.
+const MAX_STATES: usize = 200_000;
+/// Don't look for solutions longer than this many moves.
+const MAX_DEPTH: usize = 200;
+/// Solution counts saturate at this value.
+pub(crate) const SOLUTION_COUNT_CAP: usize = 1000;
+
+const ACTIONS: [Action; 5] = [
+    Action::Move(Dir4::North),
+    Action::Move(Dir4::South),
+    Action::Move(Dir4::East),
+    Action::Move(Dir4::West),
+    Action::Stall,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SolverStatus {
+    /// Still expanding states.
+    Searching,
+    /// Found the shortest solution length and counted the solutions of that length.
+    Solved,
+    /// Every reachable state was explored without winning.
+    Unsolvable,
+    /// Gave up after `MAX_STATES` states or `MAX_DEPTH` moves.
+    LimitReached,
+    /// Found the shortest solution length, but hit the limit before counting every solution
+    /// of that length.
+    PartlySolved,
+    /// The level starts with no rats, so it can't be won.
+    NoRats,
+}
+
+/// One byte per cell, so the visited set stays small. Every cell gets its own byte.
+fn cell_code(cell: Cell) -> u8 {
+    match cell {
+        Cell::Empty => 0,
+        Cell::Wall => 1,
+        Cell::Plank => 2,
+        Cell::Spiderweb => 3,
+        Cell::BlackHole => 4,
+        Cell::Explosive => 5,
+        Cell::Player(dir) => 8 + dir as u8,
+        Cell::Rat(dir) => 16 + dir as u8,
+        Cell::CyborgRat(dir) => 24 + dir as u8,
+        // Trigger digits are 1 to 9
+        Cell::Trigger(n) => 32 + n,
+    }
+}
+
+/// Cells of a search state in row order. The grid size never changes during a search.
+fn state_key(grid: &Grid) -> Box<[u8]> {
+    grid.cells()
+        .iter()
+        .flatten()
+        .map(|&c| cell_code(c))
+        .collect()
+}
+
+/// A visited state in the breadth-first search.
+struct Node {
+    depth: usize,
+    /// Number of distinct shortest move sequences reaching this state, capped.
+    paths: usize,
+    /// Predecessors one move closer to the start, with the move taken from each.
+    parents: Vec<(usize, Action)>,
+}
+
+/// Incremental breadth-first search for the shortest ways to win a level.
+///
+/// Work is done in small steps so the editor can keep rendering while it runs.
+pub(crate) struct Solver {
+    initial_cells: Vec<Vec<Cell>>,
+    status: SolverStatus,
+    nodes: Vec<Node>,
+    index: HashMap<Box<[u8]>, usize>,
+    /// States waiting to be expanded, in order of depth.
+    queue: VecDeque<(usize, Grid)>,
+    /// Winning states at the shortest solution depth.
+    wins: Vec<usize>,
+    max_states: usize,
+}
+
+impl Solver {
+    pub(crate) fn new(initial: &Grid) -> Self {
+        // Portals and notes don't affect move resolution, so leave them out of search states
+        let root = Grid::new(initial.cells().to_vec(), HashMap::new(), HashMap::new());
+        let status = match root.play_state() {
+            PlayState::Won => SolverStatus::NoRats,
+            PlayState::GameOver => SolverStatus::Unsolvable,
+            PlayState::Playing => SolverStatus::Searching,
+        };
+        let mut index = HashMap::new();
+        index.insert(state_key(&root), 0);
+        Self {
+            initial_cells: root.cells().to_vec(),
+            status,
+            nodes: vec![Node {
+                depth: 0,
+                paths: 1,
+                parents: Vec::new(),
+            }],
+            index,
+            queue: VecDeque::from([(0, root)]),
+            wins: Vec::new(),
+            max_states: MAX_STATES,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_max_states(mut self, max_states: usize) -> Self {
+        self.max_states = max_states;
+        self
+    }
+
+    /// Returns true if this search started from the given grid's cells.
+    pub(crate) fn is_for(&self, grid: &Grid) -> bool {
+        self.initial_cells == grid.cells()
+    }
+
+    pub(crate) fn status(&self) -> SolverStatus {
+        self.status
+    }
+
+    pub(crate) fn states_explored(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Length of the shortest solution, once found.
+    pub(crate) fn shortest(&self) -> Option<usize> {
+        let &win = self.wins.first()?;
+        Some(self.nodes[win].depth)
+    }
+
+    /// Number of distinct shortest solutions, capped at `SOLUTION_COUNT_CAP`.
+    pub(crate) fn solution_count(&self) -> usize {
+        let total: usize = self.wins.iter().map(|&w| self.nodes[w].paths).sum();
+        total.min(SOLUTION_COUNT_CAP)
+    }
+
+    /// Expand up to `budget` states. Returns true once the search has finished.
+    pub(crate) fn step(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            if self.status != SolverStatus::Searching {
+                return true;
+            }
+
+            let Some(&(current, _)) = self.queue.front() else {
+                self.status = if self.wins.is_empty() {
+                    SolverStatus::Unsolvable
+                } else {
+                    SolverStatus::Solved
+                };
+                return true;
+            };
+
+            // Once a win is found, only finish the layer that leads into it
+            let depth = self.nodes[current].depth;
+            if let Some(shortest) = self.shortest()
+                && depth + 1 > shortest
+            {
+                self.status = SolverStatus::Solved;
+                return true;
+            }
+            if depth >= MAX_DEPTH || self.nodes.len() >= self.max_states {
+                self.status = if self.wins.is_empty() {
+                    SolverStatus::LimitReached
+                } else {
+                    SolverStatus::PartlySolved
+                };
+                return true;
+            }
+
+            let (current, grid) = self.queue.pop_front().unwrap();
+            self.expand(current, &grid);
+        }
+        self.status != SolverStatus::Searching
+    }
+
+    fn expand(&mut self, current: usize, grid: &Grid) {
+        let depth = self.nodes[current].depth + 1;
+        let paths = self.nodes[current].paths;
+
+        for action in ACTIONS {
+            let mut next = grid.clone();
+            let mut resolver = MoveHandler::new(&mut next);
+            resolver.do_player_move(action);
+            resolver.resolve_all();
+
+            let play_state = next.play_state();
+            if play_state == PlayState::GameOver {
+                continue;
+            }
+
+            let key = state_key(&next);
+            if let Some(&existing) = self.index.get(&key) {
+                // Another shortest route to a state first reached in this layer
+                let node = &mut self.nodes[existing];
+                if node.depth == depth {
+                    node.paths = (node.paths + paths).min(SOLUTION_COUNT_CAP);
+                    node.parents.push((current, action));
+                }
+                continue;
+            }
+
+            let id = self.nodes.len();
+            self.nodes.push(Node {
+                depth,
+                paths,
+                parents: vec![(current, action)],
+            });
+            self.index.insert(key, id);
+
+            if play_state == PlayState::Won {
+                self.wins.push(id);
+            } else {
+                self.queue.push_back((id, next));
+            }
+        }
+    }
+
+    /// The `k`th distinct shortest solution, for `k < solution_count()`.
+    pub(crate) fn solution(&self, mut k: usize) -> Option<Vec<Action>> {
+        let mut node = *self.wins.iter().find(|&&w| {
+            let paths = self.nodes[w].paths;
+            if k < paths {
+                true
+            } else {
+                k -= paths;
+                false
+            }
+        })?;
+
+        let mut actions = Vec::new();
+        while let Some(&(parent, action)) = self.nodes[node].parents.iter().find(|&&(p, _)| {
+            let paths = self.nodes[p].paths;
+            if k < paths {
+                true
+            } else {
+                k -= paths;
+                false
+            }
+        }) {
+            actions.push(action);
+            node = parent;
+        }
+        actions.reverse();
+        Some(actions)
+    }
+}
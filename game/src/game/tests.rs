@@ -1,6 +1,6 @@
 use super::*;
 use crate::direction::Dir8;
-use crate::grid::{Cell, Grid};
+use crate::grid::{Cell, Grid, LevelMetadata};
 use std::collections::HashSet;
 
 fn game_from_csv(csv: &str) -> Game {
@@ -182,6 +182,85 @@ fn restart_resets_game() {
     game.restart();
     assert_eq!(player_pos(&game), initial);
     assert_eq!(game.state.history.len(), 1);
+    assert!(game.state.actions.is_empty());
+}
+
+#[test]
+fn actions_track_history_through_undo() {
+    let mut game = game_from_csv(".,.,.\n.,v,.\n.,.,.");
+    game.apply_action(Action::Move(Dir4::East));
+    game.apply_action(Action::Stall);
+    game.undo();
+    assert_eq!(game.state.actions, vec![Action::Move(Dir4::East)]);
+    assert_eq!(game.state.actions.len() + 1, game.state.history.len());
+}
+
+#[test]
+fn redo_takes_back_undo() {
+    let mut game = game_from_csv(".,.,.\n.,v,.\n.,.,.");
+    game.apply_action(Action::Move(Dir4::East));
+    let moved = player_pos(&game);
+    game.undo();
+    assert!(game.state.can_redo());
+    game.redo();
+    assert_eq!(player_pos(&game), moved);
+    assert_eq!(game.state.actions, vec![Action::Move(Dir4::East)]);
+    assert!(!game.state.can_redo());
+}
+
+#[test]
+fn new_action_clears_redo() {
+    let mut game = game_from_csv(".,.,.\n.,v,.\n.,.,.");
+    game.apply_action(Action::Move(Dir4::East));
+    game.undo();
+    game.apply_action(Action::Move(Dir4::West));
+    assert!(!game.state.can_redo());
+    game.redo();
+    assert_eq!(game.state.actions, vec![Action::Move(Dir4::West)]);
+}
+
+#[test]
+fn undo_takes_back_restart() {
+    let mut game = game_from_csv(".,.,.\n.,v,.\n.,.,.");
+    game.apply_action(Action::Move(Dir4::East));
+    game.apply_action(Action::Move(Dir4::South));
+    let before = player_pos(&game);
+    game.restart();
+    assert!(game.state.can_undo());
+    game.undo();
+    assert_eq!(player_pos(&game), before);
+    assert_eq!(game.state.actions.len(), 2);
+
+    // Redo restarts again
+    game.redo();
+    assert_eq!(game.state.history.len(), 1);
+    assert!(game.state.actions.is_empty());
+}
+
+#[test]
+fn history_tree_keeps_undone_lines() {
+    let mut game = game_from_csv(".,.,.\n.,v,.\n.,.,.");
+    game.apply_action(Action::Move(Dir4::East));
+    let east = game.state.node;
+    game.undo();
+    game.apply_action(Action::Move(Dir4::West));
+    game.apply_action(Action::Stall);
+
+    let root = game.state.tree.node(HistoryTree::ROOT);
+    assert_eq!(root.children.len(), 2);
+    assert!(root.children.contains(&east));
+
+    // Jumping back to the first line rebuilds its history
+    game.go_to(east);
+    assert_eq!(game.state.actions, vec![Action::Move(Dir4::East)]);
+    assert_eq!(game.state.history.len(), 2);
+    assert_eq!(player_pos(&game), Position::new(2, 1));
+
+    // Trying a move again reuses its node
+    game.undo();
+    game.apply_action(Action::Move(Dir4::East));
+    assert_eq!(game.state.node, east);
+    assert_eq!(game.state.tree.len(), 4);
 }
 
 #[test]
@@ -656,3 +735,253 @@ fn rat_can_move_to_cyborg_vacated_position() {
         rats
     );
 }
+
+// Solver tests
+
+fn solve(csv: &str) -> Solver {
+    let mut solver = Solver::new(&Grid::from_csv(csv));
+    while !solver.step(1000) {}
+    solver
+}
+
+#[test]
+fn solver_finds_one_move_win() {
+    let solver = solve(".,.,.\n.,>,R\n.,.,.");
+    assert_eq!(solver.status(), SolverStatus::Solved);
+    assert_eq!(solver.shortest(), Some(1));
+    assert_eq!(solver.solution(0), Some(vec![Action::Move(Dir4::East)]));
+}
+
+#[test]
+fn solver_solutions_are_distinct_and_win() {
+    let csv = "R,.,.,.\n.,.,.,.\n.,.,.,.\n.,.,.,v";
+    let solver = solve(csv);
+    assert_eq!(solver.status(), SolverStatus::Solved);
+    let shortest = solver.shortest().unwrap();
+    let count = solver.solution_count();
+    assert!(count > 1);
+
+    let mut seen = Vec::new();
+    for k in 0..count {
+        let solution = solver.solution(k).unwrap();
+        assert_eq!(solution.len(), shortest);
+        assert!(!seen.contains(&solution));
+
+        let mut game = game_from_csv(csv);
+        for &action in &solution {
+            game.apply_action(action);
+        }
+        assert_eq!(game.state.play_state(), PlayState::Won);
+        seen.push(solution);
+    }
+    assert_eq!(solver.solution(count), None);
+}
+
+#[test]
+fn solver_keeps_wins_found_before_the_limit() {
+    let csv = "R,.,.,.\n.,.,.,.\n.,.,.,.\n.,.,.,v";
+    let full = solve(csv);
+    let shortest = full.shortest();
+
+    // Some limit falls while the layer with the wins is being finished
+    let partial = (1..full.states_explored())
+        .map(|max_states| {
+            let mut solver = Solver::new(&Grid::from_csv(csv)).with_max_states(max_states);
+            while !solver.step(1000) {}
+            solver
+        })
+        .find(|solver| solver.status() != SolverStatus::LimitReached)
+        .unwrap();
+    assert_eq!(partial.status(), SolverStatus::PartlySolved);
+    assert_eq!(partial.shortest(), shortest);
+    assert!(partial.solution_count() >= 1);
+    assert!(partial.solution_count() < full.solution_count());
+}
+
+#[test]
+fn solver_reports_unsolvable() {
+    // Rat walled off from the player
+    let solver = solve(">,#,R");
+    assert_eq!(solver.status(), SolverStatus::Unsolvable);
+    assert_eq!(solver.shortest(), None);
+}
+
+#[test]
+fn solver_reports_level_without_rats() {
+    let solver = solve(".,v,.");
+    assert_eq!(solver.status(), SolverStatus::NoRats);
+}
+
+#[test]
+fn move_notation_round_trips() {
+    let moves = vec![
+        Action::Move(Dir4::North),
+        Action::Move(Dir4::East),
+        Action::Stall,
+        Action::Move(Dir4::South),
+        Action::Move(Dir4::West),
+    ];
+    let text = Action::format_moves(&moves);
+    assert_eq!(text, "NE.SW");
+    assert_eq!(Action::parse_moves(&text), Some(moves));
+    assert_eq!(Action::parse_moves("NEX"), None);
+    assert_eq!(
+        Action::parse_moves("n e ."),
+        Some(vec![
+            Action::Move(Dir4::North),
+            Action::Move(Dir4::East),
+            Action::Stall,
+        ])
+    );
+}
+
+// Resolution trace tests
+
+#[test]
+fn trace_records_phases_in_resolution_order() {
+    // Two rats: the nearer one (by squared distance) moves first
+    let grid = Grid::from_csv(".,.,.,.,R\n.,v,.,.,.\n.,.,.,R,.");
+    let steps = trace_action(&grid, Action::Stall);
+    let phases: Vec<Phase> = steps.iter().map(|s| s.phase).collect();
+    assert_eq!(
+        phases,
+        vec![
+            Phase::PlayerMove(Action::Stall),
+            Phase::Rat {
+                pos: Position::new(3, 2),
+                dist_sq: 5,
+            },
+            Phase::Rat {
+                pos: Position::new(4, 0),
+                dist_sq: 10,
+            },
+            Phase::FinishMoving,
+        ]
+    );
+
+    // The last step matches instant resolution
+    let mut game = Game::new(grid, HashSet::new());
+    game.apply_action(Action::Stall);
+    assert_eq!(steps.last().unwrap().grid.cells(), game.state.grid.cells());
+}
+
+#[test]
+fn trace_records_rejected_rat_directions() {
+    // Diagonal move is walled off, so the rat falls back to moving west
+    let grid = Grid::from_csv("v,#,.\n.,.,R");
+    let steps = trace_action(&grid, Action::Stall);
+    let rat = steps
+        .iter()
+        .find(|s| matches!(s.phase, Phase::Rat { .. }))
+        .unwrap();
+    assert_eq!(
+        rat.rejected,
+        vec![(Dir8::Northwest, Rejection::Blocked(Cell::Wall))]
+    );
+    assert_eq!(rat.chosen, Some(Dir8::West));
+}
+
+#[test]
+fn cyborg_distances_count_orthogonal_and_diagonal_steps() {
+    let grid = Grid::from_csv("v,.,.\n.,#,.\n.,.,.");
+    let distances = cyborg_distances(&grid);
+    assert_eq!(distances[&Position::new(2, 0)].to_string(), "(2, 0)");
+    // The wall forces a detour around the diagonal
+    assert_eq!(distances[&Position::new(2, 2)].to_string(), "(2, 1)");
+    assert!(!distances.contains_key(&Position::new(1, 1)));
+}
+
+// Zap preview tests
+
+#[test]
+fn zap_preview_lists_new_walls_and_chained_explosions() {
+    // The zap walls in the trigger's empty neighbors and sets off the explosive next to it,
+    // which chains into the one beyond
+    let grid = Grid::from_csv(".,1,.,.\n.,.,X,X\nv,.,.,.");
+    let preview = preview_zap(&grid, 1);
+    assert_eq!(
+        preview.walls,
+        vec![
+            Position::new(0, 0),
+            Position::new(2, 0),
+            Position::new(0, 1),
+            Position::new(1, 1),
+        ]
+    );
+    assert_eq!(
+        preview.explosions,
+        vec![Position::new(2, 1), Position::new(3, 1)]
+    );
+
+    // Other digits do nothing
+    let preview = preview_zap(&grid, 2);
+    assert!(preview.walls.is_empty() && preview.explosions.is_empty());
+}
+
+// Direction and cell transform tests
+
+#[test]
+fn flipping_cells_mirrors_facing() {
+    assert_eq!(
+        Cell::Rat(Dir8::Northeast).flipped(true, false),
+        Cell::Rat(Dir8::Northwest)
+    );
+    assert_eq!(
+        Cell::CyborgRat(Dir8::Northeast).flipped(false, true),
+        Cell::CyborgRat(Dir8::Southeast)
+    );
+    assert_eq!(
+        Cell::Player(Dir4::North).flipped(true, true),
+        Cell::Player(Dir4::South)
+    );
+    assert_eq!(Cell::Trigger(3).flipped(true, true), Cell::Trigger(3));
+}
+
+#[test]
+fn rotating_cells_turns_facing_clockwise() {
+    assert_eq!(Cell::Rat(Dir8::North).rotated_cw(), Cell::Rat(Dir8::East));
+    assert_eq!(
+        Cell::CyborgRat(Dir8::Northeast).rotated_cw(),
+        Cell::CyborgRat(Dir8::Southeast)
+    );
+    assert_eq!(
+        Cell::Player(Dir4::West).rotated_cw(),
+        Cell::Player(Dir4::North)
+    );
+    assert_eq!(Cell::Wall.rotated_cw(), Cell::Wall);
+}
+
+#[test]
+fn locked_portal_opens_once_requirements_are_completed() {
+    let metadata = LevelMetadata::parse(
+        r#"{"name": "hub", "portals": [{"x": 1, "y": 0, "level": "b", "requires": ["a"]}]}"#,
+    );
+    let grid = Grid::from_csv_and_metadata(">,_", &metadata);
+
+    let mut game = Game::new(grid.clone(), HashSet::new());
+    game.apply_action(Action::Move(Dir4::East));
+    assert_eq!(game.state.standing_on_locked_portal(), Some(vec!["a"]));
+    assert_eq!(game.state.portal_destination(), None);
+    assert_eq!(game.enter_portal(), None);
+
+    let mut game = Game::new(grid, HashSet::from(["a".to_string()]));
+    game.apply_action(Action::Move(Dir4::East));
+    assert_eq!(game.state.standing_on_locked_portal(), None);
+    assert_eq!(game.state.portal_destination(), Some("b"));
+}
+
+#[test]
+fn instant_speed_skips_animation() {
+    let mut game = game_from_csv(">,_");
+    game.begin_action(Action::Move(Dir4::East), AnimationSpeed::default());
+    assert!(game.is_animating());
+
+    let mut game = game_from_csv(">,_");
+    let speed = AnimationSpeed {
+        instant: true,
+        ..AnimationSpeed::default()
+    };
+    game.begin_action(Action::Move(Dir4::East), speed);
+    assert!(!game.is_animating());
+    assert_eq!(player_pos(&game), Position::new(1, 0));
+}
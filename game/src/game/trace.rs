@@ -0,0 +1,88 @@
+use std::borrow::BorrowMut;
+
+use crate::direction::Dir8;
+use crate::grid::{Cell, Grid};
+use crate::position::Position;
+
+use super::cyborg_distance::CyborgDistance;
+use super::{Action, MoveHandler};
+
+/// A stage of move resolution, recorded after it has been applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Phase {
+    PlayerMove(Action),
+    /// A cyborg rat choosing its move. `distance` is None when it has no path to the player.
+    CyborgRat {
+        pos: Position,
+        distance: Option<CyborgDistance>,
+    },
+    /// A rat choosing its move, with its squared distance to the player.
+    Rat {
+        pos: Position,
+        dist_sq: i32,
+    },
+    FinishMoving,
+    StartZapWave,
+    FinishZapWave,
+    StartExplosionWave,
+    FinishExplosionWave,
+}
+
+/// Why a rat or cyborg rat didn't take a candidate direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Rejection {
+    /// The target cell is occupied by something the rat can't enter.
+    Blocked(Cell),
+    /// Attacking the player from the front is blocked by the sword.
+    Sword,
+    /// The target cell has no path to the player.
+    NoPath,
+    /// The target cell isn't closer to the player.
+    NotCloser,
+    /// The target cell would let the player reach the cyborg rat.
+    NextToPlayer,
+    /// A valid move, but another candidate was closer.
+    NotBest,
+}
+
+/// Snapshot of the grid after one phase of resolution.
+#[derive(Clone)]
+pub(crate) struct TraceStep {
+    pub(crate) phase: Phase,
+    /// During the player and rat phases this is the scratch grid used for blocking checks,
+    /// with earlier movers already placed at their destinations.
+    pub(crate) grid: Grid,
+    /// Directions considered and rejected by the rat in this phase, in the order tried.
+    pub(crate) rejected: Vec<(Dir8, Rejection)>,
+    /// Direction the rat moved in, if it moved.
+    pub(crate) chosen: Option<Dir8>,
+}
+
+impl<G: BorrowMut<Grid>> MoveHandler<G> {
+    /// Record a phase if tracing is enabled.
+    pub(crate) fn record(
+        &mut self,
+        phase: Phase,
+        rejected: Vec<(Dir8, Rejection)>,
+        chosen: Option<Dir8>,
+    ) {
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceStep {
+                phase,
+                grid: self.grid.borrow().clone(),
+                rejected,
+                chosen,
+            });
+        }
+    }
+}
+
+/// Resolve one action from `grid`, recording every phase along the way.
+pub(crate) fn trace_action(grid: &Grid, m: Action) -> Vec<TraceStep> {
+    let mut grid = grid.clone();
+    let mut handler = MoveHandler::new(&mut grid);
+    handler.trace = Some(Vec::new());
+    handler.do_player_move(m);
+    handler.resolve_all();
+    handler.trace.unwrap_or_default()
+}
@@ -2,9 +2,41 @@ use std::borrow::BorrowMut;
 
 use crate::direction::Dir8;
 use crate::grid::{Cell, Grid};
+use crate::position::Position;
 
 use super::{MoveHandler, Zapping};
 
+/// What zapping a trigger digit would do to a grid.
+pub(crate) struct ZapPreview {
+    /// Empty cells that become walls.
+    pub(crate) walls: Vec<Position>,
+    /// Explosives set off, directly or through chain reactions.
+    pub(crate) explosions: Vec<Position>,
+}
+
+/// Zap every `Trigger(n)` in the grid and resolve the consequences.
+/// Stepping on a trigger zaps the other ones with its digit, so this is what any of them would do,
+/// apart from the one stepped on.
+pub(crate) fn preview_zap(grid: &Grid, n: u8) -> ZapPreview {
+    let mut after = grid.clone();
+    let mut handler = MoveHandler::new(&mut after);
+    handler.triggered_numbers.push(n);
+    handler.resolve_all();
+
+    let mut preview = ZapPreview {
+        walls: Vec::new(),
+        explosions: Vec::new(),
+    };
+    for (pos, cell) in grid.entries() {
+        match (cell, after.at(pos)) {
+            (Cell::Empty, Cell::Wall) => preview.walls.push(pos),
+            (Cell::Explosive, Cell::Empty) => preview.explosions.push(pos),
+            _ => {}
+        }
+    }
+    preview
+}
+
 impl<G: BorrowMut<Grid>> MoveHandler<G> {
     pub(crate) fn start_zap_wave(&mut self) {
         let numbers: Vec<u8> = self.triggered_numbers.drain(..).collect();
@@ -1,29 +1,92 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 
 use macroquad::prelude::*;
 use quad_gamepad::GamepadContext;
 
 use crate::game::{Action, Game, PlayState};
+use crate::grid::Grid;
+use crate::history_browser::HistoryBrowser;
 use crate::input::{Input, InputState, TouchGesture};
+use crate::level_select::{Choice, LevelGraph, LevelSelect, Route};
 use crate::level_stack::LevelStack;
+use crate::level_stats::StatsTracker;
 use crate::levels;
+use crate::position::Position;
+use crate::profile_picker::ProfilePicker;
 use crate::render::{ButtonAction, ConfirmDialog, InputHints, UiState, button_at_position, button_bar_y, render};
 use crate::screen_wake;
+use crate::settings_menu::SettingsMenu;
 use crate::sprites::Sprites;
-use crate::storage::{load_completed_levels, save_completed_levels};
+use crate::storage::{
+    SavedLevel, Session, Settings, load_completed_levels, load_level_stats, load_session,
+    load_settings, save_completed_levels, save_level_stats, save_session,
+};
+
+/// Seconds between saves of the session while playing.
+const SESSION_SAVE_INTERVAL: f64 = 5.0;
+/// Seconds between saves of the time spent playing, which is otherwise saved at level changes
+/// and on quitting. Closing a browser tab can't be caught, so this bounds what's lost there.
+const TIME_SAVE_INTERVAL: f64 = 60.0;
 
 fn load_level(name: &str, completed_levels: &mut HashSet<String>) -> Game {
+    load_level_at(name, None, completed_levels)
+}
+
+/// Load a level with the player put at `start` instead of the level's own start, if given.
+fn load_level_at(
+    name: &str,
+    start: Option<Position>,
+    completed_levels: &mut HashSet<String>,
+) -> Game {
     let level = levels::get_level(name).unwrap_or_else(|| panic!("Level not found: {}", name));
-    Game::new(level.grid.clone(), mem::take(completed_levels))
+    Game::new(start_grid(&level.grid, start), mem::take(completed_levels))
+}
+
+fn start_grid(grid: &Grid, start: Option<Position>) -> Grid {
+    let mut grid = grid.clone();
+    if let Some(start) = start {
+        grid.place_player(start);
+    }
+    grid
+}
+
+/// Replay a saved level's moves from its start. None if the level no longer exists.
+fn replay_level(saved: &SavedLevel, completed_levels: &HashSet<String>) -> Option<Game> {
+    let level = levels::get_level(&saved.level)?;
+    let grid = start_grid(&level.grid, saved.start);
+    let mut game = Game::new(grid, completed_levels.clone());
+    for action in Action::parse_moves(&saved.moves)? {
+        game.apply_action(action);
+    }
+    Some(game)
+}
+
+/// Rebuild the level stack and current game from a saved session.
+fn restore_session(
+    session: &Session,
+    completed_levels: &HashSet<String>,
+) -> Option<(Game, LevelStack)> {
+    let (first, rest) = session.levels.split_first()?;
+    let mut stack = LevelStack::new(first.level.clone(), first.start);
+    let mut game = replay_level(first, completed_levels)?;
+    for saved in rest {
+        stack.enter_level(&game, saved.level.clone(), saved.start);
+        game = replay_level(saved, completed_levels)?;
+    }
+    Some((game, stack))
 }
 
 fn button_action_to_input(action: ButtonAction) -> Input {
     match action {
         ButtonAction::Reset => Input::Restart,
         ButtonAction::Undo => Input::Undo,
+        ButtonAction::Redo => Input::Redo,
         ButtonAction::Exit => Input::Exit,
         ButtonAction::Stall => Input::Confirm,
+        ButtonAction::History => Input::History,
+        ButtonAction::Levels => Input::Levels,
+        ButtonAction::Settings => Input::Settings,
     }
 }
 
@@ -34,25 +97,128 @@ pub struct App {
     gamepad: GamepadContext,
     sprites: Sprites,
     confirm_dialog: ConfirmDialog,
+    /// Open while browsing the history tree, which takes over input.
+    history_browser: Option<HistoryBrowser>,
+    /// Shown instead of the game while choosing a level to jump to.
+    level_select: Option<LevelSelect>,
+    /// Levels reachable from the world, for showing progress through hubs.
+    level_graph: LevelGraph,
+    /// Preferences, kept up to date with the settings menu.
+    settings: Settings,
+    /// Shown instead of the game while changing preferences.
+    settings_menu: Option<SettingsMenu>,
+    /// Profile whose progress is loaded and saved.
+    profile: u32,
+    /// Shown instead of the game until a profile is chosen.
+    profile_picker: Option<ProfilePicker>,
+    /// Session as last saved, and when it was last checked for changes.
+    saved_session: Option<Session>,
+    last_session_save: f64,
+    last_time_save: f64,
+    /// Stats for each level the profile played.
+    stats: StatsTracker,
 }
 
 impl App {
     pub fn new(sprites: Sprites) -> Self {
         screen_wake::request();
+        prevent_quit();
 
-        let mut completed = load_completed_levels();
-        let game = load_level("world", &mut completed);
-        let stack = LevelStack::new("world".to_string());
+        // The world stands in until the profile picker hands over a profile
         Self {
-            game,
-            stack,
+            game: load_level("world", &mut HashSet::new()),
+            stack: LevelStack::new("world".to_string(), None),
             input: InputState::new(),
             gamepad: GamepadContext::new(),
             sprites,
             confirm_dialog: ConfirmDialog::None,
+            history_browser: None,
+            level_select: None,
+            level_graph: LevelGraph::new("world"),
+            settings: load_settings(),
+            settings_menu: None,
+            profile: 0,
+            profile_picker: Some(ProfilePicker::new()),
+            saved_session: None,
+            last_session_save: get_time(),
+            last_time_save: get_time(),
+            stats: StatsTracker::new(HashMap::new()),
         }
     }
 
+    /// Load a profile's progress, picking up where its last session left off.
+    fn start_profile(&mut self, profile: u32) {
+        let mut completed = load_completed_levels(profile);
+        let saved_session = load_session(profile);
+        let (game, stack) = saved_session
+            .as_ref()
+            .and_then(|session| restore_session(session, &completed))
+            .unwrap_or_else(|| {
+                (
+                    load_level("world", &mut completed),
+                    LevelStack::new("world".to_string(), None),
+                )
+            });
+        self.game = game;
+        self.stack = stack;
+        self.profile = profile;
+        self.profile_picker = None;
+        self.settings = load_settings();
+        self.saved_session = saved_session;
+        self.last_session_save = get_time();
+        self.last_time_save = get_time();
+        self.stats = StatsTracker::new(load_level_stats(profile));
+        self.stats.reset_counted(&self.game);
+        self.input.reset();
+    }
+
+    /// Run one frame of the profile picker, starting the game once a profile is chosen.
+    fn tick_profile_picker(&mut self) {
+        let dt = get_frame_time();
+        let hints = self.input_hints();
+        let inputs = self.input.poll_keyboard_gamepad(&self.gamepad, dt);
+        let tap = match self.input.poll_touch() {
+            Some(TouchGesture::Tap(pos)) => Some(pos),
+            _ => self.input.poll_mouse_click(),
+        };
+        let Some(picker) = &mut self.profile_picker else {
+            return;
+        };
+        if let Some(profile) = picker.update(&inputs, tap, hints) {
+            self.start_profile(profile);
+        }
+    }
+
+    /// Save the level stack, moves and level stats, skipping what's unchanged since the last save.
+    /// Time spent playing only counts as a change `with_time`, so idle play isn't saved every few
+    /// seconds.
+    fn save_session(&mut self, with_time: bool) {
+        self.last_session_save = get_time();
+        if with_time {
+            self.last_time_save = self.last_session_save;
+        }
+        let session = self.stack.session(&self.game);
+        if self.saved_session.as_ref() != Some(&session) {
+            save_session(self.profile, &session);
+            self.saved_session = Some(session);
+        }
+        if let Some(stats) = self.stats.unsaved(with_time) {
+            save_level_stats(self.profile, stats);
+        }
+    }
+
+    fn undo(&mut self) {
+        if self.game.state.can_undo() {
+            self.game.undo();
+            self.stats.undone(&self.stack.current_level, &self.game);
+        }
+    }
+
+    fn redo(&mut self) {
+        self.game.redo();
+        self.stats.redone(&self.game);
+    }
+
     fn input_hints(&self) -> InputHints {
         if let Some(gp) = self.gamepad.gamepad(0)
             && gp.is_connected()
@@ -69,9 +235,11 @@ impl App {
     fn ui_state(&self) -> UiState {
         UiState {
             can_reset: self.game.state.history.len() > 1,
-            can_undo: self.game.state.history.len() > 1,
+            can_undo: self.game.state.can_undo(),
+            can_redo: self.game.state.can_redo(),
+            can_browse: self.game.state.tree.len() > 1,
             can_exit: true,
-            on_portal: self.game.state.standing_on_portal().is_some(),
+            on_portal: self.game.enter_portal().is_some(),
         }
     }
 
@@ -80,10 +248,12 @@ impl App {
         let was_won = self.game.state.play_state() == PlayState::Won;
         if let Some(restored) = self.stack.exit_level(&self.game) {
             if was_won {
-                save_completed_levels(&restored.state.completed_levels);
+                save_completed_levels(self.profile, &restored.state.completed_levels);
             }
             self.game = restored;
+            self.stats.reset_counted(&self.game);
             self.input.reset();
+            self.save_session(true);
         }
     }
 
@@ -95,8 +265,25 @@ impl App {
                 }
             }
             Input::Undo => {
-                self.game.undo();
+                self.undo();
+            }
+            Input::Redo => {
+                self.redo();
+            }
+            Input::History => {
+                if self.game.state.tree.len() > 1 {
+                    self.history_browser = Some(HistoryBrowser::new(&self.game));
+                }
+            }
+            Input::Levels => {
+                self.level_select = Some(LevelSelect::new(
+                    "world",
+                    &self.stack.current_level,
+                    &self.game.state.completed_levels,
+                    self.stats.all(),
+                ));
             }
+            Input::Settings => self.settings_menu = Some(SettingsMenu::new()),
             Input::Exit => {
                 self.confirm_dialog = if self.stack.can_exit() {
                     ConfirmDialog::Exit
@@ -115,13 +302,15 @@ impl App {
                     if let Some(level) = self.game.enter_portal().map(str::to_string) {
                         self.do_portal_transition(&level);
                     } else {
-                        self.game.try_begin_action(Action::Stall);
+                        self.game
+                            .try_begin_action(Action::Stall, self.settings.animation);
                     }
                 }
             }
             Input::Move(dir) => {
                 if self.game.state.play_state() == PlayState::Playing {
-                    self.game.try_begin_action(Action::Move(dir));
+                    self.game
+                        .try_begin_action(Action::Move(dir), self.settings.animation);
                 }
             }
         }
@@ -144,26 +333,153 @@ impl App {
             if play_state == PlayState::Won && self.stack.can_exit() {
                 self.exit_level();
             } else if play_state == PlayState::GameOver {
-                self.game.undo();
+                self.undo();
             }
         }
     }
 
+    /// Route input to the history browser while it's open.
+    fn tick_history_browser(&mut self, dt: f32) {
+        let mut inputs = self.input.poll_keyboard_gamepad(&self.gamepad, dt);
+        let tap = match self.input.poll_touch() {
+            Some(TouchGesture::Swipe(dir)) => {
+                inputs.push(Input::Move(dir));
+                None
+            }
+            Some(TouchGesture::Tap(pos)) => Some(pos),
+            None => self.input.poll_mouse_click(),
+        };
+        // The History button closes the browser, like the key that opened it
+        let hints = self.input_hints();
+        let ui = self.ui_state();
+        let is_playing = self.game.state.play_state() == PlayState::Playing;
+        let font = self.sprites.font();
+        let on_history_button = tap.is_some_and(|pos| {
+            button_at_position(pos, &ui, is_playing, hints, button_bar_y(), font)
+                == Some(ButtonAction::History)
+        });
+
+        let Some(browser) = &mut self.history_browser else {
+            return;
+        };
+        let mut open = inputs
+            .into_iter()
+            .all(|input| browser.handle_input(&mut self.game, input));
+        if open && on_history_button {
+            open = false;
+        } else if open && let Some(pos) = tap {
+            browser.handle_tap(&mut self.game, pos);
+        }
+        if !open {
+            self.history_browser = None;
+        }
+        // Jumping around the tree isn't taking turns
+        self.stats.reset_counted(&self.game);
+    }
+
+    /// Run one frame of the level select, jumping to a level once one is chosen.
+    fn tick_level_select(&mut self) {
+        let dt = get_frame_time();
+        let mut inputs = self.input.poll_keyboard_gamepad(&self.gamepad, dt);
+        let tap = match self.input.poll_touch() {
+            Some(TouchGesture::Swipe(dir)) => {
+                inputs.push(Input::Move(dir));
+                None
+            }
+            Some(TouchGesture::Tap(pos)) => Some(pos),
+            None => self.input.poll_mouse_click(),
+        };
+        let Some(select) = &mut self.level_select else {
+            return;
+        };
+        match select.update(&inputs, tap) {
+            Some(Choice::Level(level)) => {
+                let route = select.route(&level);
+                self.level_select = None;
+                self.jump_to_level(&route);
+            }
+            Some(Choice::Cancel) => self.level_select = None,
+            None => {}
+        }
+    }
+
+    /// Run one frame of the settings menu, applying changes as they're made.
+    fn tick_settings_menu(&mut self) {
+        let dt = get_frame_time();
+        let inputs = self.input.poll_keyboard_gamepad(&self.gamepad, dt);
+        let tap = match self.input.poll_touch() {
+            Some(TouchGesture::Tap(pos)) => Some(pos),
+            _ => self.input.poll_mouse_click(),
+        };
+        let Some(menu) = &mut self.settings_menu else {
+            return;
+        };
+        let open = menu.update(&inputs, tap);
+        self.settings = menu.settings();
+        if !open {
+            self.settings_menu = None;
+        }
+    }
+
+    /// Start a level by entering every hub on the way to it with the player on the portal
+    /// leading on, so the level stack is the same as if the player had got there on foot.
+    fn jump_to_level(&mut self, route: &Route) {
+        let Some((first, start)) = route.first() else {
+            return;
+        };
+        self.game = load_level_at(first, *start, &mut self.game.state.completed_levels);
+        self.stack = LevelStack::new(first.clone(), *start);
+        for (level, start) in &route[1..] {
+            self.enter_level(level, *start);
+        }
+        // Only the chosen level is attempted, not the hubs passed through
+        let level = self.stack.current_level.clone();
+        self.stats.entered(&level, &self.game);
+        self.input.reset();
+        self.save_session(true);
+    }
+
     fn do_portal_transition(&mut self, level: &str) {
+        self.enter_level(level, None);
+        self.stats.entered(level, &self.game);
+        self.input.reset();
+    }
+
+    /// Push the current game onto the level stack and load `level` in its place.
+    fn enter_level(&mut self, level: &str, start: Option<Position>) {
         let level = level.to_string();
 
-        self.stack.enter_level(&self.game, level.clone());
-        self.game = load_level(&level, &mut self.game.state.completed_levels);
+        self.stack.enter_level(&self.game, level.clone(), start);
+        self.game = load_level_at(&level, start, &mut self.game.state.completed_levels);
 
         // Auto-complete levels with no rats
         if !self.game.initial_has_rats() {
             self.game
                 .state
                 .mark_level_completed(&self.stack.current_level);
-            save_completed_levels(&self.game.state.completed_levels);
+            save_completed_levels(self.profile, &self.game.state.completed_levels);
+        }
+    }
+
+    /// Reload the current level from disk and start it over (only while levels are read from disk).
+    fn reload_current_level(&mut self) {
+        let level = self.stack.current_level.clone();
+        if levels::reload_level(&level) {
+            self.level_graph = LevelGraph::new("world");
+            self.game = load_level(&level, &mut self.game.state.completed_levels);
+            self.stats.reset_counted(&self.game);
+            self.input.reset();
         }
+    }
 
-        self.input.reset();
+    /// Solved and total levels reached through `hub`.
+    fn hub_progress(&self, hub: &str) -> (usize, usize) {
+        let levels = self.level_graph.levels_under(hub);
+        let solved = levels
+            .iter()
+            .filter(|&&level| self.game.is_level_completed(level))
+            .count();
+        (solved, levels.len())
     }
 
     fn handle_portal_transition(&mut self) {
@@ -181,9 +497,39 @@ impl App {
     /// Run one frame of the game loop. Returns false if the game should exit.
     pub fn tick(&mut self) -> bool {
         self.gamepad.poll();
+        // Closing the window is held back by `prevent_quit` until the session is saved
+        if is_quit_requested() {
+            if self.profile_picker.is_none() {
+                self.save_session(true);
+            }
+            return false;
+        }
+        if self.profile_picker.is_some() {
+            self.tick_profile_picker();
+            self.render();
+            return true;
+        }
+        if self.level_select.is_some() {
+            self.tick_level_select();
+            self.render();
+            return true;
+        }
+        if self.settings_menu.is_some() {
+            self.tick_settings_menu();
+            self.render();
+            return true;
+        }
         let dt = get_frame_time();
 
-        self.handle_portal_transition();
+        // Positions visited while browsing history shouldn't send the player through portals
+        if self.history_browser.is_none() {
+            self.handle_portal_transition();
+        }
+
+        // Pick up level edits without a rebuild
+        if cfg!(debug_assertions) && is_key_pressed(KeyCode::F5) {
+            self.reload_current_level();
+        }
 
         // Handle confirmation dialog input
         if self.confirm_dialog != ConfirmDialog::None {
@@ -206,18 +552,22 @@ impl App {
                 match self.confirm_dialog {
                     ConfirmDialog::Restart => {
                         self.game.restart();
+                        self.stats.restarted(&self.stack.current_level, &self.game);
                         self.input.reset();
                     }
                     ConfirmDialog::Exit => {
                         self.exit_level();
                     }
                     ConfirmDialog::QuitGame => {
+                        self.save_session(true);
                         return false;
                     }
                     ConfirmDialog::None => {}
                 }
                 self.confirm_dialog = ConfirmDialog::None;
             }
+        } else if self.history_browser.is_some() {
+            self.tick_history_browser(dt);
         } else {
             for action in self.input.poll_keyboard_gamepad(&self.gamepad, dt) {
                 self.handle_input(action);
@@ -235,7 +585,13 @@ impl App {
             }
         }
 
-        self.game.animate(dt);
+        self.game.animate(dt, self.settings.animation);
+        self.stats.update(&self.stack.current_level, &self.game, dt);
+        if get_time() - self.last_time_save >= TIME_SAVE_INTERVAL {
+            self.save_session(true);
+        } else if get_time() - self.last_session_save >= SESSION_SAVE_INTERVAL {
+            self.save_session(false);
+        }
         self.render();
 
         true
@@ -243,23 +599,79 @@ impl App {
 
     fn render(&mut self) {
         let hints = self.input_hints();
+        if let Some(picker) = &self.profile_picker {
+            picker.render(hints, self.sprites.font());
+            self.gamepad.end_frame();
+            return;
+        }
+        if let Some(select) = &self.level_select {
+            select.render(hints, self.sprites.font());
+            self.gamepad.end_frame();
+            return;
+        }
+        if let Some(menu) = &self.settings_menu {
+            menu.render(hints, self.sprites.font());
+            self.gamepad.end_frame();
+            return;
+        }
         let ui = self.ui_state();
 
-        // Priority: note text > completed portal name > current level name
+        // Priority: note text > locked portal > completed portal name > current level name
         let note_text = self.game.state.standing_on_note();
-        let portal_name = self.game.state.standing_on_completed_portal();
-        let level_name =
-            levels::get_level(&self.stack.current_level).map(|l| l.display_name.as_str());
-        let description = note_text.or(portal_name).or(level_name);
+        let locked = self.game.state.standing_on_locked_portal().map(|missing| {
+            let names: Vec<_> = missing
+                .iter()
+                .map(|&level| levels::display_name(level))
+                .collect();
+            format!("Locked: complete {} first", names.join(", "))
+        });
+        let portal_name = self.game.state.standing_on_completed_portal().map(|name| {
+            let best = self
+                .game
+                .state
+                .standing_on_portal()
+                .and_then(|level| self.stats.get(level)?.best_moves);
+            match best {
+                Some(best) => format!("{} (best: {} moves)", name, best),
+                None => name,
+            }
+        });
+        let current = &self.stack.current_level;
+        let level_name = levels::get_level(current).map(|l| {
+            let (solved, total) = self.hub_progress(current);
+            if current == "world" && total > 0 {
+                format!("{} ({}% complete)", l.display_name, solved * 100 / total)
+            } else {
+                l.display_name.clone()
+            }
+        });
+        let description = note_text
+            .or(locked.as_deref())
+            .or(portal_name.as_deref())
+            .or(level_name.as_deref());
+
+        // Portals into hubs show how much of what lies beyond is solved
+        let hub_portals: HashMap<_, _> = self
+            .game
+            .state
+            .grid
+            .portals()
+            .filter(|&(_, level)| self.level_graph.is_hub(level))
+            .map(|(pos, level)| (pos, self.hub_progress(level)))
+            .collect();
 
         render(
             &self.game,
             &self.sprites,
             description,
+            &hub_portals,
             &ui,
             hints,
             self.confirm_dialog,
         );
+        if let Some(browser) = &self.history_browser {
+            browser.render(&self.game, hints, self.sprites.font());
+        }
         self.gamepad.end_frame();
     }
 }
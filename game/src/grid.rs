@@ -7,7 +7,7 @@ use crate::position::Position;
 mod parse;
 pub(crate) use parse::LevelMetadata;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum Cell {
     Empty,
     Wall,
@@ -37,6 +37,46 @@ impl Cell {
     pub(crate) fn blocks_cyborg_rat(&self) -> bool {
         matches!(self, Cell::Wall | Cell::CyborgRat(_) | Cell::Spiderweb)
     }
+
+    /// The same cell with its facing mirrored left-right and/or top-bottom.
+    pub(crate) fn flipped(self, flip_x: bool, flip_y: bool) -> Cell {
+        match self {
+            Cell::Player(mut dir) => {
+                if flip_x {
+                    dir = dir.flip_x();
+                }
+                if flip_y {
+                    dir = dir.flip_y();
+                }
+                Cell::Player(dir)
+            }
+            Cell::Rat(dir) | Cell::CyborgRat(dir) => {
+                let mut flipped = dir;
+                if flip_x {
+                    flipped = flipped.flip_x();
+                }
+                if flip_y {
+                    flipped = flipped.flip_y();
+                }
+                if matches!(self, Cell::Rat(_)) {
+                    Cell::Rat(flipped)
+                } else {
+                    Cell::CyborgRat(flipped)
+                }
+            }
+            cell => cell,
+        }
+    }
+
+    /// The same cell with its facing turned a quarter turn clockwise.
+    pub(crate) fn rotated_cw(self) -> Cell {
+        match self {
+            Cell::Player(dir) => Cell::Player(dir.rotate_cw()),
+            Cell::Rat(dir) => Cell::Rat(dir.rotate_cw()),
+            Cell::CyborgRat(dir) => Cell::CyborgRat(dir.rotate_cw()),
+            cell => cell,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -45,6 +85,8 @@ pub(crate) struct Grid {
     width: usize,
     height: usize,
     portals: HashMap<Position, String>,
+    /// Levels that must be completed before a portal opens. Only portals with requirements are listed.
+    portal_requirements: HashMap<Position, Vec<String>>,
     notes: HashMap<Position, String>,
 }
 
@@ -64,6 +106,7 @@ impl Grid {
             width,
             height,
             portals,
+            portal_requirements: HashMap::new(),
             notes,
         }
     }
@@ -75,6 +118,7 @@ impl Grid {
             width,
             height,
             portals: HashMap::new(),
+            portal_requirements: HashMap::new(),
             notes: HashMap::new(),
         }
     }
@@ -114,6 +158,11 @@ impl Grid {
         self.height
     }
 
+    /// Rows of cells, without portals or notes.
+    pub(crate) fn cells(&self) -> &[Vec<Cell>] {
+        &self.cells
+    }
+
     pub(crate) fn at(&self, pos: Position) -> Cell {
         if pos.in_bounds(self.bounds()) {
             self.cells[pos.y as usize][pos.x as usize]
@@ -166,6 +215,8 @@ impl Grid {
         // Remove portals and notes outside new bounds
         self.portals
             .retain(|pos, _| (pos.x as usize) < new_width && (pos.y as usize) < new_height);
+        self.portal_requirements
+            .retain(|pos, _| (pos.x as usize) < new_width && (pos.y as usize) < new_height);
         self.notes
             .retain(|pos, _| (pos.x as usize) < new_width && (pos.y as usize) < new_height);
     }
@@ -186,6 +237,23 @@ impl Grid {
 
     pub(crate) fn remove_portal(&mut self, pos: Position) {
         self.portals.remove(&pos);
+        self.portal_requirements.remove(&pos);
+    }
+
+    /// Levels that must be completed before the portal at `pos` opens.
+    pub(crate) fn portal_requires(&self, pos: Position) -> &[String] {
+        self.portal_requirements
+            .get(&pos)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_portal_requires(&mut self, pos: Position, requires: Vec<String>) {
+        if requires.is_empty() {
+            self.portal_requirements.remove(&pos);
+        } else {
+            self.portal_requirements.insert(pos, requires);
+        }
     }
 
     pub(crate) fn get_note(&self, pos: Position) -> Option<&str> {
@@ -204,6 +272,17 @@ impl Grid {
         self.notes.remove(&pos);
     }
 
+    /// Move the player to `pos`, facing the same way.
+    pub(crate) fn place_player(&mut self, pos: Position) {
+        let player = self
+            .find_entities(|cell| matches!(cell, Cell::Player(_)))
+            .next();
+        if let Some((from, cell)) = player {
+            *self.at_mut(from) = Cell::Empty;
+            *self.at_mut(pos) = cell;
+        }
+    }
+
     pub(crate) fn find_entities<F: FnMut(Cell) -> bool>(
         &self,
         mut f: F,
@@ -230,7 +309,12 @@ impl Grid {
         }
     }
 
-    pub(crate) fn to_json(&self, level_name: &str) -> String {
-        LevelMetadata::from_grid(level_name, &self.portals, &self.notes).to_json()
+    pub(crate) fn metadata(&self, level_name: &str) -> LevelMetadata {
+        LevelMetadata::from_grid(
+            level_name,
+            &self.portals,
+            &self.portal_requirements,
+            &self.notes,
+        )
     }
 }
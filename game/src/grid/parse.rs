@@ -8,23 +8,29 @@ use crate::position::Position;
 
 use super::{Cell, Grid};
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, PartialEq)]
 pub(crate) struct LevelMetadata {
     pub(crate) name: String,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     portals: Vec<Portal>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     notes: Vec<Note>,
+    /// Reference solution in move notation (see `Action::format_moves`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) solution: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq)]
 struct Portal {
     x: i32,
     y: i32,
     level: String,
+    /// Levels that must be completed before this portal opens.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    requires: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq)]
 struct Note {
     x: i32,
     y: i32,
@@ -33,12 +39,17 @@ struct Note {
 
 impl LevelMetadata {
     pub(crate) fn parse(json_str: &str) -> Self {
-        serde_json::from_str(json_str).expect("invalid JSON")
+        Self::try_parse(json_str).expect("invalid JSON")
+    }
+
+    pub(crate) fn try_parse(json_str: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json_str)
     }
 
     pub(crate) fn from_grid(
         name: &str,
         portals: &HashMap<Position, String>,
+        portal_requirements: &HashMap<Position, Vec<String>>,
         notes: &HashMap<Position, String>,
     ) -> Self {
         let mut portals: Vec<_> = portals
@@ -47,6 +58,7 @@ impl LevelMetadata {
                 x: pos.x,
                 y: pos.y,
                 level: level.clone(),
+                requires: portal_requirements.get(pos).cloned().unwrap_or_default(),
             })
             .collect();
         portals.sort_by_key(|p| (p.y, p.x));
@@ -65,6 +77,7 @@ impl LevelMetadata {
             name: name.to_string(),
             portals,
             notes,
+            solution: None,
         }
     }
 
@@ -79,6 +92,20 @@ impl LevelMetadata {
             .collect()
     }
 
+    /// Requirements of the portals that have any, keyed like `portals`.
+    pub(crate) fn portal_requirements(&self) -> HashMap<Position, Vec<String>> {
+        self.portals
+            .iter()
+            .filter(|p| !p.requires.is_empty())
+            .map(|p| {
+                (
+                    Position::new(p.x as usize, p.y as usize),
+                    p.requires.clone(),
+                )
+            })
+            .collect()
+    }
+
     pub(crate) fn notes(&self) -> HashMap<Position, String> {
         self.notes
             .iter()
@@ -90,18 +117,40 @@ impl LevelMetadata {
 impl Grid {
     #[cfg(test)]
     pub(crate) fn from_csv(csv_str: &str) -> Self {
-        Self::parse_csv(csv_str, HashMap::new(), HashMap::new())
+        Self::parse_csv(csv_str, HashMap::new(), HashMap::new(), true).unwrap()
     }
 
     pub(crate) fn from_csv_and_metadata(csv_str: &str, metadata: &LevelMetadata) -> Self {
-        Self::parse_csv(csv_str, metadata.portals(), metadata.notes())
+        Self::try_from_csv_and_metadata(csv_str, metadata).unwrap()
+    }
+
+    /// Like `from_csv_and_metadata`, but reports invalid CSV or a missing player instead of panicking.
+    pub(crate) fn try_from_csv_and_metadata(
+        csv_str: &str,
+        metadata: &LevelMetadata,
+    ) -> Result<Self, String> {
+        let mut grid = Self::parse_csv(csv_str, metadata.portals(), metadata.notes(), true)?;
+        grid.portal_requirements = metadata.portal_requirements();
+        Ok(grid)
+    }
+
+    /// Parse part of a level, such as an editor stamp, which needn't contain a player.
+    /// Without one, rats face the top-left corner like newly placed ones in the editor.
+    pub(crate) fn fragment_from_csv_and_metadata(
+        csv_str: &str,
+        metadata: &LevelMetadata,
+    ) -> Result<Self, String> {
+        let mut grid = Self::parse_csv(csv_str, metadata.portals(), metadata.notes(), false)?;
+        grid.portal_requirements = metadata.portal_requirements();
+        Ok(grid)
     }
 
     fn parse_csv(
         csv_str: &str,
         portals: HashMap<Position, String>,
         notes: HashMap<Position, String>,
-    ) -> Self {
+        require_player: bool,
+    ) -> Result<Self, String> {
         let mut cells: Vec<Vec<Cell>> = Vec::new();
         let mut player_pos: Option<Position> = None;
         let mut rat_positions: Vec<Position> = Vec::new();
@@ -113,7 +162,7 @@ impl Grid {
             .from_reader(csv_str.as_bytes());
 
         for (y, result) in reader.records().enumerate() {
-            let record = result.expect("invalid CSV");
+            let record = result.map_err(|e| format!("invalid CSV: {}", e))?;
             let mut row = Vec::new();
             for (x, field) in record.iter().enumerate() {
                 let pos = Position::new(x, y);
@@ -163,8 +212,15 @@ impl Grid {
             cells.push(row);
         }
 
+        let player = match player_pos {
+            Some(pos) => pos,
+            None if require_player => return Err("no player in level".to_string()),
+            None => Position::new(0, 0),
+        };
+        if cells.is_empty() {
+            return Err("empty CSV".to_string());
+        }
         let mut grid = Grid::new(cells, portals, notes);
-        let player = player_pos.unwrap();
         for rat in rat_positions {
             let dir = rat.direction_to(player);
             *grid.at_mut(rat) = Cell::Rat(dir);
@@ -173,6 +229,6 @@ impl Grid {
             let dir = cyborg.direction_to(player);
             *grid.at_mut(cyborg) = Cell::CyborgRat(dir);
         }
-        grid
+        Ok(grid)
     }
 }
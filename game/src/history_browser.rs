@@ -0,0 +1,259 @@
+use std::mem;
+
+use macroquad::prelude::*;
+use quad_gamepad::ControllerType;
+
+use crate::direction::Dir4;
+use crate::game::{Game, HistoryTree, PlayState, Undone};
+use crate::input::Input;
+use crate::render::{InputHints, button_bar_y, dialogue_y, draw_text_f};
+
+const NODE_SPACING_X: f32 = 22.0;
+const NODE_SPACING_Y: f32 = 20.0;
+const NODE_RADIUS: f32 = 5.0;
+const HEADER_HEIGHT: f32 = 32.0;
+const PADDING: f32 = 12.0;
+
+/// Overlay over the dialogue area for moving around the history tree.
+/// The game follows along, so the grid shows the position being browsed.
+pub(crate) struct HistoryBrowser {
+    /// Where the game was when the browser opened, to go back to if cancelled.
+    start: usize,
+    start_redo: Vec<Undone>,
+    start_restarted: Vec<usize>,
+    /// Row each node is drawn in. A position's first move continues its row, other moves start new rows.
+    lanes: Vec<usize>,
+    /// Number of moves from the initial grid to each node.
+    depths: Vec<usize>,
+    /// Row that moving forward heads for, so going back and forth keeps to the same line.
+    lane: usize,
+}
+
+/// Lay out the tree depth first, so each line's branches sit just below it.
+fn layout(tree: &HistoryTree) -> (Vec<usize>, Vec<usize>) {
+    let mut lanes = vec![0; tree.len()];
+    let mut depths = vec![0; tree.len()];
+    let mut next_lane = 1;
+    // Branches get their row when visited, after everything on the lines above them
+    let mut stack = vec![(HistoryTree::ROOT, Some(0), 0)];
+    while let Some((id, lane, depth)) = stack.pop() {
+        let lane = lane.unwrap_or_else(|| {
+            next_lane += 1;
+            next_lane - 1
+        });
+        lanes[id] = lane;
+        depths[id] = depth;
+        for (i, &child) in tree.node(id).children.iter().enumerate().rev() {
+            stack.push((child, (i == 0).then_some(lane), depth + 1));
+        }
+    }
+    (lanes, depths)
+}
+
+fn panel_rect(game: &Game) -> Rect {
+    let y = dialogue_y(game);
+    Rect::new(0.0, y, screen_width(), button_bar_y() - y)
+}
+
+impl HistoryBrowser {
+    pub(crate) fn new(game: &Game) -> Self {
+        let (lanes, depths) = layout(&game.state.tree);
+        Self {
+            start: game.state.node,
+            start_redo: game.state.redo.clone(),
+            start_restarted: game.state.restarted.clone(),
+            lane: lanes[game.state.node],
+            lanes,
+            depths,
+        }
+    }
+
+    /// Arrows move through the tree, Confirm keeps the position and Exit goes back to the start.
+    /// Returns false once the browser is closed.
+    pub(crate) fn handle_input(&mut self, game: &mut Game, input: Input) -> bool {
+        let tree = &game.state.tree;
+        let node = game.state.node;
+        let target = match input {
+            Input::Move(Dir4::West) | Input::Undo => tree.node(node).parent,
+            Input::Move(Dir4::East) | Input::Redo => {
+                // Follow the line heading for the remembered row
+                let children = &tree.node(node).children;
+                children
+                    .iter()
+                    .rev()
+                    .find(|&&c| self.lanes[c] <= self.lane)
+                    .or(children.first())
+                    .copied()
+            }
+            Input::Move(Dir4::North) => {
+                self.nearest_in_column(node, |lane| lane < self.lanes[node])
+            }
+            Input::Move(Dir4::South) => {
+                self.nearest_in_column(node, |lane| lane > self.lanes[node])
+            }
+            Input::Confirm | Input::History => return false,
+            Input::Exit => {
+                game.go_to(self.start);
+                game.state.redo = mem::take(&mut self.start_redo);
+                game.state.restarted = mem::take(&mut self.start_restarted);
+                return false;
+            }
+            Input::Restart | Input::Levels | Input::Settings => None,
+        };
+        if let Some(target) = target {
+            if matches!(input, Input::Move(Dir4::North | Dir4::South)) {
+                self.lane = self.lanes[target];
+            }
+            game.go_to(target);
+        }
+        true
+    }
+
+    /// The node with as many moves as `node` in the closest row matching `accept`.
+    fn nearest_in_column(&self, node: usize, accept: impl Fn(usize) -> bool) -> Option<usize> {
+        let lane = self.lanes[node];
+        (0..self.lanes.len())
+            .filter(|&id| self.depths[id] == self.depths[node] && accept(self.lanes[id]))
+            .min_by_key(|&id| self.lanes[id].abs_diff(lane))
+    }
+
+    /// Jump to the node under a tap or click.
+    pub(crate) fn handle_tap(&mut self, game: &mut Game, pos: Vec2) {
+        let positions = self.node_positions(game);
+        if let Some(id) = (0..positions.len())
+            .filter(|&id| positions[id].distance(pos) <= NODE_SPACING_Y / 2.0)
+            .min_by(|&a, &b| {
+                positions[a]
+                    .distance(pos)
+                    .total_cmp(&positions[b].distance(pos))
+            })
+        {
+            self.lane = self.lanes[id];
+            game.go_to(id);
+        }
+    }
+
+    /// Screen position of every node, scrolled to keep the current one in view.
+    fn node_positions(&self, game: &Game) -> Vec<Vec2> {
+        let panel = panel_rect(game);
+        let node = game.state.node;
+        let top = panel.y + HEADER_HEIGHT + NODE_RADIUS;
+        let area_h = panel.h - HEADER_HEIGHT - PADDING;
+        let scroll_x = (self.depths[node] as f32 * NODE_SPACING_X - panel.w / 2.0).max(0.0);
+        let scroll_y = (self.lanes[node] as f32 * NODE_SPACING_Y - area_h / 2.0).max(0.0);
+        (0..self.lanes.len())
+            .map(|id| {
+                vec2(
+                    PADDING + self.depths[id] as f32 * NODE_SPACING_X - scroll_x,
+                    top + self.lanes[id] as f32 * NODE_SPACING_Y - scroll_y,
+                )
+            })
+            .collect()
+    }
+
+    fn hint(hints: InputHints) -> &'static str {
+        use ControllerType::*;
+        match hints {
+            InputHints::Keyboard => {
+                "←/→ along a line | ↑/↓ other lines | Space to keep | Esc to go back"
+            }
+            InputHints::Touch => "Tap a move to jump there | History to keep",
+            InputHints::Controller(Xbox | Generic) => {
+                "D-pad to browse | A to keep | Menu to go back"
+            }
+            InputHints::Controller(PlayStation) => {
+                "D-pad to browse | ✕ to keep | Options to go back"
+            }
+            InputHints::Controller(Nintendo) => "D-pad to browse | B to keep | + to go back",
+        }
+    }
+
+    pub(crate) fn render(&self, game: &Game, hints: InputHints, font: &Font) {
+        let panel = panel_rect(game);
+        let tree = &game.state.tree;
+
+        // Background
+        draw_rectangle(
+            panel.x,
+            panel.y,
+            panel.w,
+            panel.h,
+            Color::from_rgba(20, 20, 30, 255),
+        );
+
+        // Top border
+        draw_line(
+            panel.x,
+            panel.y,
+            panel.x + panel.w,
+            panel.y,
+            2.0,
+            Color::from_rgba(60, 60, 80, 255),
+        );
+
+        let header = format!(
+            "Move {} | {}",
+            self.depths[game.state.node],
+            Self::hint(hints)
+        );
+        draw_text_f(
+            &header,
+            PADDING,
+            panel.y + 22.0,
+            font,
+            18,
+            Color::from_rgba(200, 200, 220, 255),
+        );
+
+        let mut on_path = vec![false; tree.len()];
+        for id in tree.path(game.state.node) {
+            on_path[id] = true;
+        }
+
+        // Only draw what's inside the panel, below the header
+        let positions = self.node_positions(game);
+        let visible = |p: Vec2| {
+            p.y >= panel.y + HEADER_HEIGHT
+                && p.y <= panel.y + panel.h
+                && p.x >= -NODE_SPACING_X
+                && p.x <= panel.w + NODE_SPACING_X
+        };
+
+        // Lines from each position to the moves tried from it
+        for (id, &pos) in positions.iter().enumerate() {
+            let Some(parent) = tree.node(id).parent else {
+                continue;
+            };
+            let from = positions[parent];
+            if !visible(pos) && !visible(from) {
+                continue;
+            }
+            let color = if on_path[id] {
+                Color::from_rgba(200, 200, 220, 255)
+            } else {
+                Color::from_rgba(90, 90, 110, 255)
+            };
+            draw_line(from.x, from.y, pos.x, pos.y, 2.0, color);
+        }
+
+        // Positions, colored by how the game stands there
+        for (id, &pos) in positions.iter().enumerate() {
+            if !visible(pos) {
+                continue;
+            }
+            let color = match game.state.play_state_of(&tree.node(id).grid) {
+                PlayState::Won => GREEN,
+                PlayState::GameOver => RED,
+                PlayState::Playing if on_path[id] => WHITE,
+                PlayState::Playing => GRAY,
+            };
+            draw_circle(pos.x, pos.y, NODE_RADIUS, color);
+            if id == self.start {
+                draw_circle_lines(pos.x, pos.y, NODE_RADIUS + 3.0, 2.0, SKYBLUE);
+            }
+            if id == game.state.node {
+                draw_circle_lines(pos.x, pos.y, NODE_RADIUS + 4.0, 2.0, YELLOW);
+            }
+        }
+    }
+}
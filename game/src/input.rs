@@ -13,9 +13,13 @@ const SWIPE_THRESHOLD: f32 = 30.0;
 pub(crate) enum Input {
     Move(Dir4),
     Undo,
+    Redo,
     Restart,
     Exit,
     Confirm,
+    History,
+    Levels,
+    Settings,
 }
 
 /// A touch gesture result.
@@ -29,6 +33,7 @@ pub(crate) enum TouchGesture {
 pub(crate) struct InputState {
     held: [f32; 4],
     held_undo: f32,
+    held_redo: f32,
     held_confirm: f32,
     stick_active: [bool; 4],
     touch_start: Option<(u64, Vec2)>,
@@ -40,6 +45,7 @@ impl InputState {
         Self {
             held: [0.0; 4],
             held_undo: 0.0,
+            held_redo: 0.0,
             held_confirm: 0.0,
             stick_active: [false; 4],
             touch_start: None,
@@ -50,6 +56,7 @@ impl InputState {
     pub(crate) fn reset(&mut self) {
         self.held = [0.0; 4];
         self.held_undo = 0.0;
+        self.held_redo = 0.0;
         self.held_confirm = 0.0;
         self.stick_active = [false; 4];
     }
@@ -86,6 +93,21 @@ impl InputState {
             inputs.push(Input::Undo);
         }
 
+        // Redo with repeat (Y / RB / RT)
+        let redo_down = is_down_multi(
+            KeyCode::Y,
+            &[GamepadButton::RightShoulder, GamepadButton::RightTrigger],
+            gamepad,
+        );
+        let redo_pressed = is_pressed_multi(
+            KeyCode::Y,
+            &[GamepadButton::RightShoulder, GamepadButton::RightTrigger],
+            gamepad,
+        );
+        if input_repeat(redo_down, redo_pressed, &mut self.held_redo, dt) {
+            inputs.push(Input::Redo);
+        }
+
         // Confirm with repeat (Space / A / B)
         let confirm_down = is_down_multi(
             KeyCode::Space,
@@ -106,6 +128,21 @@ impl InputState {
             inputs.push(Input::Exit);
         }
 
+        // History browser (H / Back/Select)
+        if is_pressed_multi(KeyCode::H, &[GamepadButton::Select], gamepad) {
+            inputs.push(Input::History);
+        }
+
+        // Level select (L / right stick press)
+        if is_pressed_multi(KeyCode::L, &[GamepadButton::RightStick], gamepad) {
+            inputs.push(Input::Levels);
+        }
+
+        // Settings (O / left stick press)
+        if is_pressed_multi(KeyCode::O, &[GamepadButton::LeftStick], gamepad) {
+            inputs.push(Input::Settings);
+        }
+
         // D-pad and keyboard arrows with repeat
         for (key, btn, dir, idx) in [
             (KeyCode::Up, GamepadButton::DPadUp, Dir4::North, 0),
@@ -0,0 +1,527 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use macroquad::prelude::*;
+use quad_gamepad::ControllerType;
+
+use crate::direction::Dir4;
+use crate::input::Input;
+use crate::levels::{self, display_name};
+use crate::position::Position;
+use crate::render::{InputHints, draw_text_f, measure_text_f};
+use crate::storage::LevelStats;
+
+const LIST_TOP: f32 = 90.0;
+const LIST_WIDTH: f32 = 560.0;
+const ROW_HEIGHT: f32 = 36.0;
+const BUTTON_WIDTH: f32 = 140.0;
+const BUTTON_HEIGHT: f32 = 36.0;
+const BOTTOM_SAFE_AREA: f32 = 80.0;
+
+/// Every level reachable from a root level through portals.
+pub(crate) struct LevelGraph {
+    /// The hub and portal position each level is first reached from.
+    parents: HashMap<String, (String, Position)>,
+    /// Levels with portals, in the order they were reached, with the levels first reached from them.
+    hubs: Vec<(String, Vec<String>)>,
+    /// Levels the portal to each level requires (see `Grid::portal_requires`), if any.
+    requires: HashMap<String, Vec<String>>,
+}
+
+impl LevelGraph {
+    pub(crate) fn new(root: &str) -> Self {
+        let mut parents = HashMap::new();
+        let mut hubs = Vec::new();
+        let mut requires = HashMap::new();
+        let mut seen = HashSet::from([root.to_string()]);
+        let mut queue = VecDeque::from([root.to_string()]);
+        while let Some(hub) = queue.pop_front() {
+            let Some(level) = levels::get_level(&hub) else {
+                continue;
+            };
+            let mut portals: Vec<_> = level.grid.portals().collect();
+            if portals.is_empty() {
+                continue;
+            }
+            portals.sort_by_key(|&(pos, _)| (pos.y, pos.x));
+
+            let mut reached = Vec::new();
+            for (pos, target) in portals {
+                if seen.insert(target.to_string()) {
+                    parents.insert(target.to_string(), (hub.clone(), pos));
+                    let required = level.grid.portal_requires(pos);
+                    if !required.is_empty() {
+                        requires.insert(target.to_string(), required.to_vec());
+                    }
+                    queue.push_back(target.to_string());
+                    reached.push(target.to_string());
+                }
+            }
+            hubs.push((hub, reached));
+        }
+        Self {
+            parents,
+            hubs,
+            requires,
+        }
+    }
+
+    /// Levels required by portals on the way to `level` that aren't in `completed`.
+    pub(crate) fn missing_requirements(
+        &self,
+        level: &str,
+        completed: &HashSet<String>,
+    ) -> Vec<String> {
+        let mut missing = Vec::new();
+        for (step, _) in self.route(level) {
+            for required in self.requires.get(&step).into_iter().flatten() {
+                if !completed.contains(required) && !missing.contains(required) {
+                    missing.push(required.clone());
+                }
+            }
+        }
+        missing
+    }
+
+    fn reached_from(&self, hub: &str) -> Option<&[String]> {
+        self.hubs
+            .iter()
+            .find(|(h, _)| h == hub)
+            .map(|(_, reached)| reached.as_slice())
+    }
+
+    /// Whether `level` was found to have portals of its own.
+    pub(crate) fn is_hub(&self, level: &str) -> bool {
+        self.reached_from(level).is_some()
+    }
+
+    /// Levels without portals that are reached through `hub`, directly or through its sub-hubs.
+    pub(crate) fn levels_under(&self, hub: &str) -> Vec<&str> {
+        let mut levels = Vec::new();
+        let mut stack = vec![hub];
+        while let Some(hub) = stack.pop() {
+            for level in self.reached_from(hub).unwrap_or_default() {
+                if self.is_hub(level) {
+                    stack.push(level);
+                } else {
+                    levels.push(level.as_str());
+                }
+            }
+        }
+        levels
+    }
+
+    /// Levels from the root to `level`. Each level but the last comes with the portal leading on.
+    pub(crate) fn route(&self, level: &str) -> Vec<(String, Option<Position>)> {
+        let mut route = vec![(level.to_string(), None)];
+        let mut current = level;
+        while let Some((hub, portal)) = self.parents.get(current) {
+            route.push((hub.clone(), Some(*portal)));
+            current = hub;
+        }
+        route.reverse();
+        route
+    }
+}
+
+enum Row {
+    Hub(String),
+    Level {
+        path: String,
+        name: String,
+        completed: bool,
+        best_moves: Option<u32>,
+        /// Display names of levels that portals on the way still require.
+        missing: Vec<String>,
+    },
+}
+
+/// What the player picked on the level select screen.
+pub(crate) enum Choice {
+    Cancel,
+    Level(String),
+}
+
+/// Levels from the root to a chosen one, each but the last with the portal leading on.
+pub(crate) type Route = Vec<(String, Option<Position>)>;
+
+/// Menu listing every level reachable from the world, grouped by the hub whose portal leads to it.
+pub(crate) struct LevelSelect {
+    graph: LevelGraph,
+    rows: Vec<Row>,
+    /// Highlighted row, always a level.
+    selected: usize,
+    /// How far the list is scrolled, in rows.
+    scroll: usize,
+}
+
+fn list_rect() -> (f32, f32) {
+    let w = LIST_WIDTH.min(screen_width() - 40.0);
+    ((screen_width() - w) / 2.0, w)
+}
+
+fn visible_rows() -> usize {
+    let bottom = screen_height() - BOTTOM_SAFE_AREA - BUTTON_HEIGHT - 10.0;
+    (((bottom - LIST_TOP) / ROW_HEIGHT) as usize).max(1)
+}
+
+fn back_button_rect() -> Rect {
+    Rect::new(
+        (screen_width() - BUTTON_WIDTH) / 2.0,
+        screen_height() - BOTTOM_SAFE_AREA - BUTTON_HEIGHT,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+    )
+}
+
+impl LevelSelect {
+    /// List the levels reachable from `root`, starting at `current` if it's among them.
+    pub(crate) fn new(
+        root: &str,
+        current: &str,
+        completed: &HashSet<String>,
+        stats: &HashMap<String, LevelStats>,
+    ) -> Self {
+        Self::from_graph(LevelGraph::new(root), current, completed, stats)
+    }
+
+    fn from_graph(
+        graph: LevelGraph,
+        current: &str,
+        completed: &HashSet<String>,
+        stats: &HashMap<String, LevelStats>,
+    ) -> Self {
+        let mut select = Self {
+            graph,
+            rows: Vec::new(),
+            selected: 0,
+            scroll: 0,
+        };
+        let mut rows = Vec::new();
+        for (hub, reached) in &select.graph.hubs {
+            rows.push(Row::Hub(display_name(hub)));
+            rows.extend(reached.iter().map(|path| {
+                Row::Level {
+                    path: path.clone(),
+                    name: display_name(path),
+                    completed: completed.contains(path),
+                    best_moves: stats.get(path).and_then(|s| s.best_moves),
+                    missing: select
+                        .graph
+                        .missing_requirements(path, completed)
+                        .iter()
+                        .map(|level| display_name(level))
+                        .collect(),
+                }
+            }));
+        }
+        let is_level = |row: &Row| matches!(row, Row::Level { .. });
+        let selected = rows
+            .iter()
+            .position(|row| matches!(row, Row::Level { path, .. } if path == current))
+            .or_else(|| rows.iter().position(is_level))
+            .unwrap_or(0);
+        select.rows = rows;
+        select.selected = selected;
+        select
+    }
+
+    /// Hubs and portals leading from the root to `level`, for building the level stack.
+    pub(crate) fn route(&self, level: &str) -> Route {
+        self.graph.route(level)
+    }
+
+    fn is_level(&self, row: usize) -> bool {
+        matches!(self.rows.get(row), Some(Row::Level { .. }))
+    }
+
+    /// Move the highlight to the next level row in a direction, if there is one.
+    fn step(&mut self, forward: bool) {
+        let mut row = self.selected;
+        loop {
+            row = if forward {
+                row + 1
+            } else {
+                match row.checked_sub(1) {
+                    Some(row) => row,
+                    None => return,
+                }
+            };
+            if row >= self.rows.len() {
+                return;
+            }
+            if self.is_level(row) {
+                self.selected = row;
+                return;
+            }
+        }
+    }
+
+    /// Move the highlight to the first level of the next or previous hub.
+    fn step_hub(&mut self, forward: bool) {
+        let hub_starts: Vec<usize> = (0..self.rows.len())
+            .filter(|&row| !self.is_level(row) && self.is_level(row + 1))
+            .map(|row| row + 1)
+            .collect();
+        let target = if forward {
+            hub_starts.iter().find(|&&row| row > self.selected)
+        } else {
+            hub_starts.iter().rev().find(|&&row| row < self.selected)
+        };
+        if let Some(&row) = target {
+            self.selected = row;
+        }
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let visible = visible_rows();
+        // Show the hub heading above the first level of a section too
+        let top = self.selected.saturating_sub(1);
+        if top < self.scroll {
+            self.scroll = top;
+        } else if self.selected >= self.scroll + visible {
+            self.scroll = self.selected + 1 - visible;
+        }
+    }
+
+    fn choose(&self) -> Option<Choice> {
+        match self.rows.get(self.selected) {
+            Some(Row::Level { path, missing, .. }) if missing.is_empty() => {
+                Some(Choice::Level(path.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Apply keyboard, gamepad and tap input for a frame.
+    pub(crate) fn update(&mut self, inputs: &[Input], tap: Option<Vec2>) -> Option<Choice> {
+        for &input in inputs {
+            match input {
+                Input::Move(Dir4::North) => self.step(false),
+                Input::Move(Dir4::South) => self.step(true),
+                Input::Move(Dir4::West) => self.step_hub(false),
+                Input::Move(Dir4::East) => self.step_hub(true),
+                Input::Confirm => return self.choose(),
+                Input::Exit | Input::Levels => return Some(Choice::Cancel),
+                _ => {}
+            }
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            return self.choose();
+        }
+        self.scroll_to_selected();
+
+        let pos = tap?;
+        if back_button_rect().contains(pos) {
+            return Some(Choice::Cancel);
+        }
+        let (x, w) = list_rect();
+        if pos.x < x || pos.x >= x + w || pos.y < LIST_TOP {
+            return None;
+        }
+        let row = self.scroll + ((pos.y - LIST_TOP) / ROW_HEIGHT) as usize;
+        if row >= self.scroll + visible_rows() || !self.is_level(row) {
+            return None;
+        }
+        // Tapping the highlighted level again plays it
+        if row == self.selected {
+            return self.choose();
+        }
+        self.selected = row;
+        None
+    }
+
+    fn hint(hints: InputHints) -> &'static str {
+        use ControllerType::*;
+        match hints {
+            InputHints::Keyboard => "Space to play | ←/→ to change hub | Esc to go back",
+            InputHints::Touch => "Tap a level again to play it",
+            InputHints::Controller(Xbox | Generic) => "A to play | Menu to go back",
+            InputHints::Controller(PlayStation) => "✕ to play | Options to go back",
+            InputHints::Controller(Nintendo) => "B to play | + to go back",
+        }
+    }
+
+    pub(crate) fn render(&self, hints: InputHints, font: &Font) {
+        clear_background(Color::from_rgba(30, 30, 40, 255));
+        let center_x = screen_width() / 2.0;
+
+        // Title
+        let title = "LEVELS";
+        let title_dims = measure_text_f(title, font, 48);
+        draw_text_f(
+            title,
+            center_x - title_dims.width / 2.0,
+            LIST_TOP - 30.0,
+            font,
+            48,
+            YELLOW,
+        );
+
+        let (list_x, list_w) = list_rect();
+        let visible = visible_rows();
+        for (i, row) in self.rows.iter().enumerate().skip(self.scroll).take(visible) {
+            let y = LIST_TOP + (i - self.scroll) as f32 * ROW_HEIGHT;
+            let text_y = y + ROW_HEIGHT / 2.0 + 8.0;
+            match row {
+                Row::Hub(name) => {
+                    draw_text_f(name, list_x, text_y, font, 26, YELLOW);
+                }
+                Row::Level {
+                    name,
+                    completed,
+                    best_moves,
+                    missing,
+                    ..
+                } => {
+                    let bg = if i == self.selected {
+                        Color::from_rgba(80, 80, 100, 255)
+                    } else {
+                        Color::from_rgba(50, 50, 60, 255)
+                    };
+                    draw_rectangle(list_x, y, list_w, ROW_HEIGHT - 4.0, bg);
+                    let name_color = if missing.is_empty() { WHITE } else { GRAY };
+                    draw_text_f(name, list_x + 12.0, text_y, font, 22, name_color);
+
+                    let (status, status_color) = match (completed, best_moves) {
+                        _ if !missing.is_empty() => {
+                            (format!("locked: needs {}", missing.join(", ")), ORANGE)
+                        }
+                        (true, Some(best)) => (format!("solved, best {} moves", best), GREEN),
+                        (true, None) => ("solved".to_string(), GREEN),
+                        (false, _) => (String::new(), GREEN),
+                    };
+                    let status_dims = measure_text_f(&status, font, 20);
+                    draw_text_f(
+                        &status,
+                        list_x + list_w - status_dims.width - 12.0,
+                        text_y,
+                        font,
+                        20,
+                        status_color,
+                    );
+                }
+            }
+        }
+
+        // Back button
+        let rect = back_button_rect();
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::from_rgba(50, 50, 60, 255),
+        );
+        draw_rectangle_lines(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            1.0,
+            Color::from_rgba(70, 70, 85, 255),
+        );
+        let dims = measure_text_f("Back", font, 22);
+        draw_text_f(
+            "Back",
+            rect.x + (rect.w - dims.width) / 2.0,
+            rect.y + (rect.h + dims.height) / 2.0 - 2.0,
+            font,
+            22,
+            Color::from_rgba(220, 220, 230, 255),
+        );
+
+        // Hint
+        let hint = Self::hint(hints);
+        let hint_dims = measure_text_f(hint, font, 22);
+        draw_text_f(
+            hint,
+            center_x - hint_dims.width / 2.0,
+            screen_height() - BOTTOM_SAFE_AREA + 30.0,
+            font,
+            22,
+            GRAY,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_passes_through_sub_hubs() {
+        let graph = LevelGraph::new("world");
+        let route: Vec<_> = graph
+            .route("old_levels/overstep")
+            .into_iter()
+            .map(|(level, portal)| (level, portal.is_some()))
+            .collect();
+        assert_eq!(
+            route,
+            [
+                ("world".to_string(), true),
+                ("old_levels/old_levels".to_string(), true),
+                ("old_levels/overstep".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn levels_under_a_hub_include_sub_hub_levels() {
+        let graph = LevelGraph::new("world");
+        let world = graph.levels_under("world");
+        let old_levels = graph.levels_under("old_levels/old_levels");
+        assert!(old_levels.contains(&"old_levels/overstep"));
+        assert!(old_levels.iter().all(|level| world.contains(level)));
+        assert!(!world.contains(&"old_levels/old_levels"));
+    }
+
+    #[test]
+    fn every_level_has_a_route_through_its_hubs() {
+        let graph = LevelGraph::new("world");
+        for level in levels::level_names() {
+            let route = graph.route(level);
+            assert_eq!(
+                route[0].0, "world",
+                "{} isn't reached from the world",
+                level
+            );
+            assert_eq!(route.last().unwrap(), &(level.to_string(), None));
+            for pair in route.windows(2) {
+                let ((hub, portal), (next, _)) = (&pair[0], &pair[1]);
+                let grid = &levels::get_level(hub).unwrap().grid;
+                assert_eq!(grid.get_portal(portal.unwrap()), Some(next.as_str()));
+            }
+        }
+    }
+
+    #[test]
+    fn levels_behind_locked_portals_cannot_be_chosen() {
+        let select = LevelSelect::new("world", "world", &HashSet::new(), &HashMap::new());
+        let (row, level) = select
+            .rows
+            .iter()
+            .enumerate()
+            .find_map(|(i, row)| match row {
+                Row::Level { path, .. } => Some((i, path.clone())),
+                _ => None,
+            })
+            .unwrap();
+
+        let mut graph = LevelGraph::new("world");
+        graph
+            .requires
+            .insert(level.clone(), vec!["old_levels/overstep".to_string()]);
+        let locked = LevelSelect::from_graph(graph, &level, &HashSet::new(), &HashMap::new());
+        assert_eq!(locked.selected, row);
+        assert!(locked.choose().is_none());
+
+        let mut graph = LevelGraph::new("world");
+        graph
+            .requires
+            .insert(level.clone(), vec!["old_levels/overstep".to_string()]);
+        let completed = HashSet::from(["old_levels/overstep".to_string()]);
+        let unlocked = LevelSelect::from_graph(graph, &level, &completed, &HashMap::new());
+        assert!(matches!(unlocked.choose(), Some(Choice::Level(path)) if path == level));
+    }
+}
@@ -1,18 +1,23 @@
 use std::mem;
 
-use crate::game::{Game, PlayState};
+use crate::game::{Action, Game, PlayState};
+use crate::position::Position;
+use crate::storage::{SavedLevel, Session};
 
 /// Manages the stack of game states when navigating between levels via portals.
+/// Each level is kept with where the player was put in it instead of its own start, if anywhere.
 pub(crate) struct LevelStack {
-    stack: Vec<(Game, String)>,
+    stack: Vec<(Game, String, Option<Position>)>,
     pub(crate) current_level: String,
+    current_start: Option<Position>,
 }
 
 impl LevelStack {
-    pub(crate) fn new(initial_level: String) -> Self {
+    pub(crate) fn new(initial_level: String, start: Option<Position>) -> Self {
         Self {
             stack: Vec::new(),
             current_level: initial_level,
+            current_start: start,
         }
     }
 
@@ -21,10 +26,11 @@ impl LevelStack {
     }
 
     /// Push current state onto stack before entering a new level.
-    pub(crate) fn enter_level(&mut self, game: &Game, new_level: String) {
+    pub(crate) fn enter_level(&mut self, game: &Game, new_level: String, start: Option<Position>) {
         self.stack.push((
             game.clone(),
             mem::replace(&mut self.current_level, new_level),
+            mem::replace(&mut self.current_start, start),
         ));
     }
 
@@ -33,7 +39,7 @@ impl LevelStack {
     /// - If current level was won, marks it as completed.
     /// - If current level was not completed and was previously unvisited, undoes the portal step.
     pub(crate) fn exit_level(&mut self, current_game: &Game) -> Option<Game> {
-        let (mut saved, prev_level) = self.stack.pop()?;
+        let (mut saved, prev_level, prev_start) = self.stack.pop()?;
 
         // Transfer completed_levels from current game to saved
         saved.state.completed_levels = current_game.state.completed_levels.clone();
@@ -47,9 +53,26 @@ impl LevelStack {
         }
 
         self.current_level = prev_level;
+        self.current_start = prev_start;
 
         Some(saved)
     }
+
+    /// Every level on the stack, ending with the current one played in `current_game`.
+    pub(crate) fn session(&self, current_game: &Game) -> Session {
+        let saved = |game: &Game, level: &str, start: Option<Position>| SavedLevel {
+            level: level.to_string(),
+            moves: Action::format_moves(&game.state.actions),
+            start,
+        };
+        let mut levels: Vec<SavedLevel> = self
+            .stack
+            .iter()
+            .map(|(game, level, start)| saved(game, level, *start))
+            .collect();
+        levels.push(saved(current_game, &self.current_level, self.current_start));
+        Session { levels }
+    }
 }
 
 #[cfg(test)]
@@ -91,8 +114,8 @@ mod tests {
         assert_eq!(player_pos(&parent_game), Position::new(1, 1));
 
         // Create level stack and enter sublevel
-        let mut stack = LevelStack::new("world".to_string());
-        stack.enter_level(&parent_game, "sublevel".to_string());
+        let mut stack = LevelStack::new("world".to_string(), None);
+        stack.enter_level(&parent_game, "sublevel".to_string(), None);
 
         // Create sublevel game (not completed - still playing)
         let sublevel_game = game_from_csv(".,.,.\n.,v,R\n.,.,.");
@@ -115,8 +138,8 @@ mod tests {
         let pos_on_portal = player_pos(&parent_game);
 
         // Create level stack and enter sublevel
-        let mut stack = LevelStack::new("world".to_string());
-        stack.enter_level(&parent_game, "sublevel".to_string());
+        let mut stack = LevelStack::new("world".to_string(), None);
+        stack.enter_level(&parent_game, "sublevel".to_string(), None);
 
         // Create sublevel game that's been won
         let mut sublevel_game = game_from_csv(".,.,.\n.,>,R\n.,.,.");
@@ -147,8 +170,8 @@ mod tests {
         let pos_on_portal = player_pos(&parent_game);
 
         // Create level stack and enter sublevel
-        let mut stack = LevelStack::new("world".to_string());
-        stack.enter_level(&parent_game, "sublevel".to_string());
+        let mut stack = LevelStack::new("world".to_string(), None);
+        stack.enter_level(&parent_game, "sublevel".to_string(), None);
 
         // Create sublevel game with inherited completed_levels (simulates how main.rs works)
         let sublevel_game = Game::new(
@@ -163,4 +186,34 @@ mod tests {
         // Player should still be on the portal (no undo - level was previously visited)
         assert_eq!(player_pos(&restored), pos_on_portal);
     }
+
+    #[test]
+    fn session_lists_stack_levels_with_their_moves_and_starts() {
+        let mut parent_game = game_with_portal_at(Position::new(1, 1), "sublevel");
+        parent_game.apply_action(Action::Move(Dir4::East));
+
+        let mut stack = LevelStack::new("world".to_string(), None);
+        let start = Some(Position::new(1, 1));
+        stack.enter_level(&parent_game, "sublevel".to_string(), start);
+
+        let mut sublevel_game = game_from_csv(".,.,.\n.,v,R\n.,.,.");
+        sublevel_game.apply_action(Action::Stall);
+
+        let levels: Vec<(String, String, Option<Position>)> = stack
+            .session(&sublevel_game)
+            .levels
+            .into_iter()
+            .map(|saved| (saved.level, saved.moves, saved.start))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                ("world".to_string(), "E".to_string(), None),
+                ("sublevel".to_string(), ".".to_string(), start),
+            ]
+        );
+
+        stack.exit_level(&sublevel_game);
+        assert_eq!(stack.session(&parent_game).levels[0].start, None);
+    }
 }
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use crate::game::{Game, PlayState};
+use crate::storage::LevelStats;
+
+/// A profile's stats for each level, counted from the game being played.
+pub(crate) struct StatsTracker {
+    /// Stats as they are now and as last saved.
+    stats: HashMap<String, LevelStats>,
+    saved: HashMap<String, LevelStats>,
+    /// Moves in the current game, and whether it was won, when turns were last counted.
+    counted_actions: usize,
+    counted_win: bool,
+    /// Seconds spent in `timed_level` that aren't in its stats yet, so that time passing
+    /// doesn't on its own count as a change worth saving.
+    timed_level: String,
+    unsaved_time: f64,
+}
+
+impl StatsTracker {
+    pub(crate) fn new(stats: HashMap<String, LevelStats>) -> Self {
+        Self {
+            saved: stats.clone(),
+            stats,
+            counted_actions: 0,
+            counted_win: false,
+            timed_level: String::new(),
+            unsaved_time: 0.0,
+        }
+    }
+
+    pub(crate) fn all(&self) -> &HashMap<String, LevelStats> {
+        &self.stats
+    }
+
+    pub(crate) fn get(&self, level: &str) -> Option<&LevelStats> {
+        self.stats.get(level)
+    }
+
+    fn level_mut(&mut self, level: &str) -> &mut LevelStats {
+        self.stats.entry(level.to_string()).or_default()
+    }
+
+    /// Start counting turns from `game`, e.g. after switching levels or jumping through history.
+    pub(crate) fn reset_counted(&mut self, game: &Game) {
+        self.counted_actions = game.state.actions.len();
+        self.counted_win = game.state.play_state() == PlayState::Won;
+    }
+
+    /// Count the turns taken and any win since the last call, and `dt` seconds spent in `level`.
+    pub(crate) fn update(&mut self, level: &str, game: &Game, dt: f32) {
+        let moves = game.state.actions.len();
+        let won = game.state.play_state() == PlayState::Won;
+        let new_turns = moves.saturating_sub(self.counted_actions) as u32;
+        let new_win = won && !self.counted_win;
+        self.counted_actions = moves;
+        self.counted_win = won;
+
+        if new_turns > 0 || new_win {
+            let stats = self.level_mut(level);
+            stats.turns += new_turns;
+            if new_win {
+                let moves = moves as u32;
+                stats.best_moves = Some(stats.best_moves.map_or(moves, |best| best.min(moves)));
+            }
+        }
+
+        if self.timed_level != level {
+            self.add_unsaved_time();
+            self.timed_level = level.to_string();
+        }
+        self.unsaved_time += dt as f64;
+    }
+
+    /// `level` was entered, through a portal or by jumping to it.
+    pub(crate) fn entered(&mut self, level: &str, game: &Game) {
+        self.reset_counted(game);
+        self.level_mut(level).attempts += 1;
+    }
+
+    /// `game` was restarted.
+    pub(crate) fn restarted(&mut self, level: &str, game: &Game) {
+        self.reset_counted(game);
+        let stats = self.level_mut(level);
+        stats.restarts += 1;
+        stats.attempts += 1;
+    }
+
+    /// A move in `game` was undone.
+    pub(crate) fn undone(&mut self, level: &str, game: &Game) {
+        self.level_mut(level).undos += 1;
+        // Undoing a restart brings back moves already counted
+        self.counted_actions = game.state.actions.len();
+    }
+
+    /// A move in `game` was redone. Moves taken again aren't new turns.
+    pub(crate) fn redone(&mut self, game: &Game) {
+        self.counted_actions = game.state.actions.len();
+    }
+
+    fn add_unsaved_time(&mut self) {
+        if self.unsaved_time > 0.0 {
+            let time = std::mem::take(&mut self.unsaved_time);
+            let level = self.timed_level.clone();
+            self.level_mut(&level).time += time;
+        }
+    }
+
+    /// Stats to save, if they changed since the last save. The time spent is added in either way,
+    /// but is only a reason to save by itself `with_time`, e.g. when leaving a level or quitting.
+    pub(crate) fn unsaved(&mut self, with_time: bool) -> Option<&HashMap<String, LevelStats>> {
+        if !with_time && self.stats == self.saved {
+            return None;
+        }
+        self.add_unsaved_time();
+        if self.stats == self.saved {
+            return None;
+        }
+        self.saved = self.stats.clone();
+        Some(&self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::direction::Dir4;
+    use crate::game::Action;
+    use crate::grid::Grid;
+
+    const LEVEL: &str = "test";
+
+    /// The player faces a rat, so moving east wins.
+    fn game() -> Game {
+        Game::new(Grid::from_csv(".,.,.\n.,>,R\n.,.,."), HashSet::new())
+    }
+
+    fn play(tracker: &mut StatsTracker, game: &mut Game, action: Action) {
+        game.apply_action(action);
+        tracker.update(LEVEL, game, 0.0);
+    }
+
+    #[test]
+    fn counts_turns_wins_undos_and_restarts() {
+        let mut game = game();
+        let mut tracker = StatsTracker::new(HashMap::new());
+        tracker.entered(LEVEL, &game);
+
+        play(&mut tracker, &mut game, Action::Stall);
+        play(&mut tracker, &mut game, Action::Move(Dir4::North));
+        game.undo();
+        tracker.undone(LEVEL, &game);
+        game.redo();
+        tracker.redone(&game);
+        tracker.update(LEVEL, &game, 0.0);
+        game.restart();
+        tracker.restarted(LEVEL, &game);
+        play(&mut tracker, &mut game, Action::Move(Dir4::East));
+
+        let stats = tracker.get(LEVEL).unwrap();
+        assert_eq!(stats.attempts, 2);
+        assert_eq!(stats.restarts, 1);
+        assert_eq!(stats.undos, 1);
+        // The redone move isn't a new turn
+        assert_eq!(stats.turns, 3);
+        assert_eq!(stats.best_moves, Some(1));
+
+        // Undoing the restart brings back moves that were already counted
+        for _ in 0..2 {
+            game.undo();
+            tracker.undone(LEVEL, &game);
+        }
+        assert_eq!(game.state.actions.len(), 2);
+        tracker.update(LEVEL, &game, 0.0);
+        assert_eq!(tracker.get(LEVEL).unwrap().turns, 3);
+    }
+
+    #[test]
+    fn time_alone_is_saved_only_when_asked() {
+        let game = game();
+        let mut tracker = StatsTracker::new(HashMap::new());
+        tracker.update(LEVEL, &game, 2.0);
+        assert!(tracker.unsaved(false).is_none());
+
+        let saved = tracker.unsaved(true).unwrap();
+        assert_eq!(saved[LEVEL].time, 2.0);
+        assert!(tracker.unsaved(true).is_none());
+
+        // Time is added in whenever something else changed
+        let mut game = game;
+        tracker.update(LEVEL, &game, 1.0);
+        game.apply_action(Action::Stall);
+        tracker.update(LEVEL, &game, 0.5);
+        let saved = tracker.unsaved(false).unwrap();
+        assert_eq!(saved[LEVEL].time, 3.5);
+        assert_eq!(saved[LEVEL].turns, 1);
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 
 use crate::grid::{Grid, LevelMetadata};
 
@@ -11,7 +11,7 @@ struct TextLevel {
 }
 
 pub(crate) struct Level {
-    pub(crate) name: &'static str,
+    pub(crate) name: String,
     pub(crate) display_name: String,
     pub(crate) grid: Grid,
 }
@@ -21,20 +21,105 @@ impl Level {
         let metadata = LevelMetadata::parse(text.json);
         let display_name = metadata.name.clone();
         Self {
-            name: text.name,
+            name: text.name.to_string(),
             display_name,
             grid: Grid::from_csv_and_metadata(text.csv, &metadata),
         }
     }
 }
 
-static LEVELS: LazyLock<Vec<Level>> = LazyLock::new(|| {
+static LEVELS: LazyLock<Vec<Arc<Level>>> = LazyLock::new(|| {
     LEVEL_DATA
         .iter()
-        .map(|(name, csv, json)| Level::parse(&TextLevel { name, csv, json }))
+        .map(|(name, csv, json)| Arc::new(Level::parse(&TextLevel { name, csv, json })))
         .collect()
 });
 
-pub(crate) fn get_level(name: &str) -> Option<&'static Level> {
-    LEVELS.iter().find(|l| l.name == name)
+pub(crate) fn get_level(name: &str) -> Option<Arc<Level>> {
+    #[cfg(all(debug_assertions, not(test), not(target_arch = "wasm32")))]
+    if let Some(level) = disk::get_level(name) {
+        return Some(level);
+    }
+    LEVELS.iter().find(|l| l.name == name).cloned()
+}
+
+/// Display name of a level, or its path if there's no such level.
+pub(crate) fn display_name(name: &str) -> String {
+    get_level(name).map_or_else(|| name.to_string(), |l| l.display_name.clone())
+}
+
+/// Paths of every embedded level, such as `world` and `old_levels/overstep`.
+pub(crate) fn level_names() -> impl Iterator<Item = &'static str> {
+    LEVEL_DATA.iter().map(|&(name, _, _)| name)
+}
+
+/// Forget the loaded copy of a level so the next `get_level` reads its files again.
+/// Returns false unless levels are being read from disk.
+pub(crate) fn reload_level(name: &str) -> bool {
+    #[cfg(all(debug_assertions, not(test), not(target_arch = "wasm32")))]
+    {
+        disk::forget_level(name)
+    }
+    #[cfg(not(all(debug_assertions, not(test), not(target_arch = "wasm32"))))]
+    {
+        let _ = name;
+        false
+    }
+}
+
+/// Debug builds started with `INFESTATION_DISK_LEVELS` set read levels straight from the
+/// source tree, so edits show up without a rebuild.
+/// Levels that are missing or fail to parse fall back to the embedded copy.
+#[cfg(all(debug_assertions, not(test), not(target_arch = "wasm32")))]
+mod disk {
+    use std::env::var_os;
+    use std::fs::read_to_string;
+    use std::sync::{Arc, LazyLock, Mutex};
+
+    use super::Level;
+    use crate::grid::{Grid, LevelMetadata};
+
+    const LEVELS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../levels");
+
+    static ENABLED: LazyLock<bool> = LazyLock::new(|| var_os("INFESTATION_DISK_LEVELS").is_some());
+
+    // Levels read so far by name, with None for ones that failed so they're not retried every frame.
+    // A reload drops the cached copy; callers still holding the old one keep it until they're done.
+    static LOADED: Mutex<Vec<(String, Option<Arc<Level>>)>> = Mutex::new(Vec::new());
+
+    pub(super) fn get_level(name: &str) -> Option<Arc<Level>> {
+        if !*ENABLED {
+            return None;
+        }
+        let mut loaded = LOADED.lock().unwrap();
+        if let Some((_, level)) = loaded.iter().find(|(n, _)| n == name) {
+            return level.clone();
+        }
+
+        let level = read_level(name)
+            .map_err(|e| log::warn!("Failed to load level {} from disk: {}", name, e))
+            .ok()
+            .map(Arc::new);
+        loaded.push((name.to_string(), level.clone()));
+        level
+    }
+
+    /// Returns false if levels aren't read from disk, so there's nothing to reload.
+    pub(super) fn forget_level(name: &str) -> bool {
+        LOADED.lock().unwrap().retain(|(n, _)| n != name);
+        *ENABLED
+    }
+
+    fn read_level(name: &str) -> Result<Level, String> {
+        let csv =
+            read_to_string(format!("{}/{}.csv", LEVELS_DIR, name)).map_err(|e| e.to_string())?;
+        let json =
+            read_to_string(format!("{}/{}.json", LEVELS_DIR, name)).map_err(|e| e.to_string())?;
+        let metadata = LevelMetadata::try_parse(&json).map_err(|e| e.to_string())?;
+        Ok(Level {
+            name: name.to_string(),
+            display_name: metadata.name.clone(),
+            grid: Grid::try_from_csv_and_metadata(&csv, &metadata)?,
+        })
+    }
 }
@@ -1,12 +1,17 @@
 pub(crate) mod direction;
 pub(crate) mod game;
 pub(crate) mod grid;
+pub(crate) mod history_browser;
 pub(crate) mod input;
+pub(crate) mod level_select;
 pub(crate) mod level_stack;
+pub(crate) mod level_stats;
 pub(crate) mod levels;
 pub(crate) mod position;
+pub(crate) mod profile_picker;
 pub(crate) mod render;
 pub(crate) mod screen_wake;
+pub(crate) mod settings_menu;
 pub(crate) mod storage;
 
 pub mod editor_app;
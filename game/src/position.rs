@@ -1,8 +1,10 @@
 use std::ops::{Add, Sub};
 
+use serde::{Deserialize, Serialize};
+
 use crate::direction::Dir8;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub(crate) struct Position {
     pub(crate) x: i32,
     pub(crate) y: i32,
@@ -27,6 +29,15 @@ impl Position {
         x_in_bounds && y_in_bounds
     }
 
+    /// The nearest position inside a grid of size `bounds`.
+    pub(crate) fn clamped(self, bounds: (usize, usize)) -> Position {
+        let (width, height) = bounds;
+        Position {
+            x: self.x.clamp(0, width as i32 - 1),
+            y: self.y.clamp(0, height as i32 - 1),
+        }
+    }
+
     pub(crate) fn direction_to(self, to: Position) -> Dir8 {
         let mut best_dir = Dir8::South;
         let mut best_dist = i32::MAX;
@@ -0,0 +1,514 @@
+use macroquad::prelude::*;
+use quad_gamepad::ControllerType;
+
+use crate::direction::Dir4;
+use crate::input::Input;
+use crate::render::{InputHints, draw_text_f, measure_text_f};
+use crate::settings_menu::SettingsMenu;
+use crate::storage::{Profiles, load_completed_levels, reset_profile};
+
+const LIST_TOP: f32 = 120.0;
+const LIST_WIDTH: f32 = 520.0;
+const ROW_HEIGHT: f32 = 48.0;
+const BUTTON_HEIGHT: f32 = 36.0;
+const BUTTON_SPACING: f32 = 6.0;
+const BOTTOM_SAFE_AREA: f32 = 80.0;
+const MAX_NAME_LEN: usize = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+enum PickerButton {
+    New,
+    Rename,
+    Reset,
+    Delete,
+    Settings,
+    Play,
+    Confirm,
+    Cancel,
+}
+
+impl PickerButton {
+    fn label(self) -> &'static str {
+        match self {
+            PickerButton::New => "New",
+            PickerButton::Rename => "Rename",
+            PickerButton::Reset => "Reset",
+            PickerButton::Delete => "Delete",
+            PickerButton::Settings => "Settings",
+            PickerButton::Play => "Play",
+            PickerButton::Confirm => "Confirm",
+            PickerButton::Cancel => "Cancel",
+        }
+    }
+}
+
+enum Mode {
+    Browse,
+    /// Typing a new name for a profile.
+    Naming {
+        id: u32,
+        name: String,
+    },
+    ConfirmReset(u32),
+    ConfirmDelete(u32),
+}
+
+/// Startup screen for choosing, creating, renaming, resetting and deleting save profiles.
+pub(crate) struct ProfilePicker {
+    profiles: Profiles,
+    /// Number of completed levels for each profile, in list order.
+    solved: Vec<usize>,
+    /// Highlighted row. One past the last profile is the "New profile" row.
+    selected: usize,
+    mode: Mode,
+    /// Shown instead of the profiles while open.
+    settings: Option<SettingsMenu>,
+    /// Why the saved profile list couldn't be read. Nothing can be played or changed until
+    /// it's fixed, since saving a new list would lose the profiles in it.
+    load_error: Option<String>,
+}
+
+fn list_rect() -> (f32, f32) {
+    let w = LIST_WIDTH.min(screen_width() - 40.0);
+    ((screen_width() - w) / 2.0, w)
+}
+
+fn row_at(pos: Vec2, rows: usize) -> Option<usize> {
+    let (x, w) = list_rect();
+    if pos.x < x || pos.x >= x + w || pos.y < LIST_TOP {
+        return None;
+    }
+    let row = ((pos.y - LIST_TOP) / ROW_HEIGHT) as usize;
+    (row < rows).then_some(row)
+}
+
+impl ProfilePicker {
+    pub(crate) fn new() -> Self {
+        let (profiles, load_error) = match Profiles::load() {
+            Ok(profiles) => (profiles, None),
+            Err(e) => {
+                log::error!("{}", e);
+                (Profiles::default(), Some(e))
+            }
+        };
+        let selected = profiles
+            .last_used
+            .and_then(|id| profiles.profiles.iter().position(|p| p.id == id))
+            .unwrap_or(0);
+        let mut picker = Self {
+            profiles,
+            solved: Vec::new(),
+            selected,
+            mode: Mode::Browse,
+            settings: None,
+            load_error,
+        };
+        picker.count_solved();
+        picker
+    }
+
+    fn count_solved(&mut self) {
+        self.solved = self
+            .profiles
+            .profiles
+            .iter()
+            .map(|p| load_completed_levels(p.id).len())
+            .collect();
+    }
+
+    fn selected_id(&self) -> Option<u32> {
+        self.profiles.profiles.get(self.selected).map(|p| p.id)
+    }
+
+    /// Typing a name takes over the keyboard, so other key bindings are ignored meanwhile.
+    fn is_naming(&self) -> bool {
+        matches!(self.mode, Mode::Naming { .. })
+    }
+
+    fn buttons(&self, hints: InputHints) -> Vec<PickerButton> {
+        match self.mode {
+            Mode::Browse => {
+                let mut buttons = vec![PickerButton::New];
+                // Naming needs a keyboard
+                if hints == InputHints::Keyboard {
+                    buttons.push(PickerButton::Rename);
+                }
+                buttons.extend([
+                    PickerButton::Reset,
+                    PickerButton::Delete,
+                    PickerButton::Settings,
+                    PickerButton::Play,
+                ]);
+                buttons
+            }
+            _ => vec![PickerButton::Confirm, PickerButton::Cancel],
+        }
+    }
+
+    fn button_rects(&self, hints: InputHints) -> Vec<(Rect, PickerButton)> {
+        let buttons = self.buttons(hints);
+        let (list_x, list_w) = list_rect();
+        let n = buttons.len() as f32;
+        let w = (list_w - BUTTON_SPACING * (n - 1.0)) / n;
+        let y = screen_height() - BOTTOM_SAFE_AREA - BUTTON_HEIGHT;
+        buttons
+            .into_iter()
+            .enumerate()
+            .map(|(i, button)| {
+                let x = list_x + i as f32 * (w + BUTTON_SPACING);
+                (Rect::new(x, y, w, BUTTON_HEIGHT), button)
+            })
+            .collect()
+    }
+
+    /// Apply keyboard, gamepad and tap input for a frame. Returns the profile to play once one is chosen.
+    pub(crate) fn update(
+        &mut self,
+        inputs: &[Input],
+        tap: Option<Vec2>,
+        hints: InputHints,
+    ) -> Option<u32> {
+        if self.load_error.is_some() {
+            return None;
+        }
+        if let Some(settings) = &mut self.settings {
+            if !settings.update(inputs, tap) {
+                self.settings = None;
+            }
+            return None;
+        }
+        if self.is_naming() {
+            self.type_name();
+        } else {
+            for &input in inputs {
+                if let Some(id) = self.handle_input(input, hints) {
+                    return Some(id);
+                }
+            }
+            if hints == InputHints::Keyboard {
+                if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+                    if let Some(id) = self.handle_input(Input::Confirm, hints) {
+                        return Some(id);
+                    }
+                } else if is_key_pressed(KeyCode::N) {
+                    self.press(PickerButton::New, hints);
+                } else if is_key_pressed(KeyCode::F2) {
+                    self.press(PickerButton::Rename, hints);
+                } else if is_key_pressed(KeyCode::Delete) {
+                    self.press(PickerButton::Delete, hints);
+                }
+            }
+        }
+
+        let pos = tap?;
+        if let Some((_, button)) = self
+            .button_rects(hints)
+            .into_iter()
+            .find(|(rect, _)| rect.contains(pos))
+        {
+            return self.press(button, hints);
+        }
+        if matches!(self.mode, Mode::Browse)
+            && let Some(row) = row_at(pos, self.profiles.profiles.len() + 1)
+        {
+            // Tapping the highlighted row again picks it
+            if row == self.selected {
+                return self.handle_input(Input::Confirm, hints);
+            }
+            self.selected = row;
+        }
+        None
+    }
+
+    fn handle_input(&mut self, input: Input, hints: InputHints) -> Option<u32> {
+        match (&self.mode, input) {
+            (Mode::Browse, Input::Move(Dir4::North)) => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            (Mode::Browse, Input::Move(Dir4::South)) => {
+                self.selected = (self.selected + 1).min(self.profiles.profiles.len());
+            }
+            (Mode::Browse, Input::Confirm) => {
+                return if self.selected_id().is_some() {
+                    self.press(PickerButton::Play, hints)
+                } else {
+                    self.press(PickerButton::New, hints)
+                };
+            }
+            (Mode::Browse, Input::Restart) => {
+                self.press(PickerButton::Reset, hints);
+            }
+            (Mode::Browse, Input::Undo) => {
+                self.press(PickerButton::Delete, hints);
+            }
+            (Mode::Browse, Input::Settings) => {
+                self.press(PickerButton::Settings, hints);
+            }
+            (Mode::ConfirmReset(_) | Mode::ConfirmDelete(_), Input::Confirm) => {
+                self.press(PickerButton::Confirm, hints);
+            }
+            (Mode::ConfirmReset(_) | Mode::ConfirmDelete(_), Input::Undo | Input::Exit) => {
+                self.press(PickerButton::Cancel, hints);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn press(&mut self, button: PickerButton, hints: InputHints) -> Option<u32> {
+        let selected = self.selected_id();
+        match (button, selected) {
+            (PickerButton::New, _) => {
+                let name = self.profiles.unused_name();
+                let id = self.profiles.create(name.clone());
+                self.profiles.save();
+                self.count_solved();
+                self.selected = self.profiles.profiles.len() - 1;
+                if hints == InputHints::Keyboard {
+                    self.mode = Mode::Naming { id, name };
+                }
+            }
+            (PickerButton::Rename, Some(id)) => {
+                let name = self.profiles.get(id).map(|p| p.name.clone())?;
+                self.mode = Mode::Naming { id, name };
+            }
+            (PickerButton::Reset, Some(id)) => self.mode = Mode::ConfirmReset(id),
+            (PickerButton::Delete, Some(id)) => self.mode = Mode::ConfirmDelete(id),
+            (PickerButton::Settings, _) => self.settings = Some(SettingsMenu::new()),
+            (PickerButton::Play, Some(id)) => {
+                self.profiles.last_used = Some(id);
+                self.profiles.save();
+                return Some(id);
+            }
+            (PickerButton::Confirm, _) => match std::mem::replace(&mut self.mode, Mode::Browse) {
+                Mode::Naming { id, name } => {
+                    let name = name.trim();
+                    if !name.is_empty() {
+                        self.profiles.rename(id, name.to_string());
+                        self.profiles.save();
+                    }
+                }
+                Mode::ConfirmReset(id) => {
+                    reset_profile(id);
+                    self.count_solved();
+                }
+                Mode::ConfirmDelete(id) => {
+                    self.profiles.delete(id);
+                    self.profiles.save();
+                    self.count_solved();
+                    self.selected = self.selected.min(self.profiles.profiles.len());
+                }
+                Mode::Browse => {}
+            },
+            (PickerButton::Cancel, _) => self.mode = Mode::Browse,
+            _ => {}
+        }
+        None
+    }
+
+    /// Edit the name being typed: characters, Backspace, Enter to keep and Escape to cancel.
+    fn type_name(&mut self) {
+        let Mode::Naming { name, .. } = &mut self.mode else {
+            return;
+        };
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && name.chars().count() < MAX_NAME_LEN {
+                name.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            name.pop();
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            self.press(PickerButton::Confirm, InputHints::Keyboard);
+        } else if is_key_pressed(KeyCode::Escape) {
+            self.mode = Mode::Browse;
+        }
+    }
+
+    fn hint(&self, hints: InputHints) -> &'static str {
+        use ControllerType::*;
+        match (&self.mode, hints) {
+            (Mode::Naming { .. }, _) => "Type a name, Enter to keep, Esc to cancel",
+            (Mode::Browse, InputHints::Keyboard) => {
+                "Space to play | N new | F2 rename | R reset | Del delete | O settings"
+            }
+            (Mode::Browse, InputHints::Touch) => "Tap a profile again to play",
+            (Mode::Browse, InputHints::Controller(Xbox | Generic)) => {
+                "A to play | LB to reset | X to delete | LS for settings"
+            }
+            (Mode::Browse, InputHints::Controller(PlayStation)) => {
+                "✕ to play | L1 to reset | □ to delete | L3 for settings"
+            }
+            (Mode::Browse, InputHints::Controller(Nintendo)) => {
+                "B to play | L to reset | Y to delete | LS for settings"
+            }
+            (_, InputHints::Keyboard) => "Space to confirm, Esc to cancel",
+            (_, InputHints::Touch) => "Tap Confirm or Cancel",
+            (_, InputHints::Controller(Xbox | Generic)) => "A to confirm, X to cancel",
+            (_, InputHints::Controller(PlayStation)) => "✕ to confirm, □ to cancel",
+            (_, InputHints::Controller(Nintendo)) => "B to confirm, Y to cancel",
+        }
+    }
+
+    fn render_load_error(error: &str, font: &Font) {
+        clear_background(Color::from_rgba(30, 30, 40, 255));
+        let center_x = screen_width() / 2.0;
+        let lines = [
+            ("CAN'T LOAD PROFILES", 48, YELLOW),
+            (error, 20, LIGHTGRAY),
+            (
+                "Fix or remove the saved profile list, then restart.",
+                24,
+                WHITE,
+            ),
+        ];
+        for (i, (text, size, color)) in lines.into_iter().enumerate() {
+            let dims = measure_text_f(text, font, size);
+            draw_text_f(
+                text,
+                center_x - dims.width / 2.0,
+                LIST_TOP - 40.0 + i as f32 * ROW_HEIGHT,
+                font,
+                size,
+                color,
+            );
+        }
+    }
+
+    pub(crate) fn render(&self, hints: InputHints, font: &Font) {
+        if let Some(error) = &self.load_error {
+            Self::render_load_error(error, font);
+            return;
+        }
+        if let Some(settings) = &self.settings {
+            settings.render(hints, font);
+            return;
+        }
+        clear_background(Color::from_rgba(30, 30, 40, 255));
+        let center_x = screen_width() / 2.0;
+
+        // Title
+        let title = "WHO'S PLAYING?";
+        let title_dims = measure_text_f(title, font, 48);
+        draw_text_f(
+            title,
+            center_x - title_dims.width / 2.0,
+            LIST_TOP - 40.0,
+            font,
+            48,
+            YELLOW,
+        );
+
+        // Profile rows, then the row for adding one
+        let (list_x, list_w) = list_rect();
+        let rows = self.profiles.profiles.len() + 1;
+        for row in 0..rows {
+            let y = LIST_TOP + row as f32 * ROW_HEIGHT;
+            let bg = if row == self.selected {
+                Color::from_rgba(80, 80, 100, 255)
+            } else {
+                Color::from_rgba(50, 50, 60, 255)
+            };
+            draw_rectangle(list_x, y, list_w, ROW_HEIGHT - 4.0, bg);
+            let text_y = y + ROW_HEIGHT / 2.0 + 8.0;
+
+            let Some(profile) = self.profiles.profiles.get(row) else {
+                draw_text_f("+ New profile", list_x + 12.0, text_y, font, 28, GRAY);
+                continue;
+            };
+            let name = match &self.mode {
+                Mode::Naming { id, name } if *id == profile.id => format!("{}_", name),
+                _ => profile.name.clone(),
+            };
+            draw_text_f(&name, list_x + 12.0, text_y, font, 28, WHITE);
+
+            let solved = format!("{} solved", self.solved.get(row).copied().unwrap_or(0));
+            let solved_dims = measure_text_f(&solved, font, 22);
+            draw_text_f(
+                &solved,
+                list_x + list_w - solved_dims.width - 12.0,
+                text_y,
+                font,
+                22,
+                LIGHTGRAY,
+            );
+        }
+
+        // Question for a pending reset or delete
+        let name_of = |id: u32| self.profiles.get(id).map_or("", |p| p.name.as_str());
+        let question = match &self.mode {
+            Mode::ConfirmReset(id) => {
+                Some(format!("Reset {}? All progress is lost.", name_of(*id)))
+            }
+            Mode::ConfirmDelete(id) => {
+                Some(format!("Delete {}? All progress is lost.", name_of(*id)))
+            }
+            _ => None,
+        };
+        if let Some(question) = question {
+            let dims = measure_text_f(&question, font, 28);
+            draw_text_f(
+                &question,
+                center_x - dims.width / 2.0,
+                LIST_TOP + rows as f32 * ROW_HEIGHT + 36.0,
+                font,
+                28,
+                YELLOW,
+            );
+        }
+
+        // Buttons
+        let enabled = |button: PickerButton| match button {
+            PickerButton::Rename
+            | PickerButton::Reset
+            | PickerButton::Delete
+            | PickerButton::Play => self.selected_id().is_some(),
+            _ => true,
+        };
+        for (rect, button) in self.button_rects(hints) {
+            let (bg_color, text_color) = if enabled(button) {
+                (
+                    Color::from_rgba(50, 50, 60, 255),
+                    Color::from_rgba(220, 220, 230, 255),
+                )
+            } else {
+                (
+                    Color::from_rgba(35, 35, 42, 255),
+                    Color::from_rgba(80, 80, 90, 255),
+                )
+            };
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, bg_color);
+            draw_rectangle_lines(
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                1.0,
+                Color::from_rgba(70, 70, 85, 255),
+            );
+            let label = button.label();
+            let dims = measure_text_f(label, font, 22);
+            draw_text_f(
+                label,
+                rect.x + (rect.w - dims.width) / 2.0,
+                rect.y + (rect.h + dims.height) / 2.0 - 2.0,
+                font,
+                22,
+                text_color,
+            );
+        }
+
+        // Hint
+        let hint = self.hint(hints);
+        let hint_dims = measure_text_f(hint, font, 22);
+        draw_text_f(
+            hint,
+            center_x - hint_dims.width / 2.0,
+            screen_height() - BOTTOM_SAFE_AREA + 30.0,
+            font,
+            22,
+            GRAY,
+        );
+    }
+}
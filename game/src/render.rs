@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use crate::game::{Game, PlayState};
 use crate::grid::Cell;
+use crate::position::Position;
 use crate::sprites::Sprites;
 use macroquad::prelude::*;
 use quad_gamepad::ControllerType;
@@ -21,11 +24,11 @@ fn text_params(font: &Font, size: u16, color: Color) -> TextParams<'_> {
     }
 }
 
-fn draw_text_f(text: &str, x: f32, y: f32, font: &Font, size: u16, color: Color) {
+pub(crate) fn draw_text_f(text: &str, x: f32, y: f32, font: &Font, size: u16, color: Color) {
     draw_text_ex(text, x, y, text_params(font, size, color));
 }
 
-fn measure_text_f(text: &str, font: &Font, size: u16) -> TextDimensions {
+pub(crate) fn measure_text_f(text: &str, font: &Font, size: u16) -> TextDimensions {
     measure_text(text, Some(font), size, 1.0)
 }
 
@@ -33,13 +36,19 @@ fn measure_text_f(text: &str, font: &Font, size: u16) -> TextDimensions {
 pub(crate) enum ButtonAction {
     Reset,
     Undo,
+    Redo,
     Stall,
+    History,
+    Levels,
+    Settings,
     Exit,
 }
 
 pub(crate) struct UiState {
     pub(crate) can_reset: bool,
     pub(crate) can_undo: bool,
+    pub(crate) can_redo: bool,
+    pub(crate) can_browse: bool,
     pub(crate) can_exit: bool,
     pub(crate) on_portal: bool,
 }
@@ -131,6 +140,7 @@ pub(crate) fn render(
     game: &Game,
     sprites: &Sprites,
     description: Option<&str>,
+    hub_portals: &HashMap<Position, (usize, usize)>,
     ui: &UiState,
     hints: InputHints,
     confirm_dialog: ConfirmDialog,
@@ -174,7 +184,25 @@ pub(crate) fn render(
 
     // Draw portals first (underneath everything else)
     for (pos, level) in game.state.grid.portals() {
-        let texture = sprites.portal(game.is_level_completed(level));
+        let locked = !game.state.missing_requirements(pos).is_empty();
+        if let Some(&(solved, total)) = hub_portals.get(&pos)
+            && !locked
+        {
+            render_hub_progress(
+                solved,
+                total,
+                offset_x + pos.x as f32 * cell,
+                offset_y + pos.y as f32 * cell,
+                cell,
+                sprites.font(),
+            );
+            continue;
+        }
+        let texture = if locked {
+            sprites.locked_portal()
+        } else {
+            sprites.portal(game.is_level_completed(level))
+        };
         draw_texture_ex(
             texture,
             offset_x + pos.x as f32 * cell,
@@ -448,6 +476,34 @@ fn render_confirm_dialog(dialog: ConfirmDialog, hints: InputHints, font: &Font)
     );
 }
 
+/// Draw a portal into a hub as a count of the levels solved beyond it.
+fn render_hub_progress(solved: usize, total: usize, x: f32, y: f32, cell: f32, font: &Font) {
+    let color = if solved == total {
+        Color::from_rgba(240, 255, 125, 255)
+    } else {
+        Color::from_rgba(44, 164, 27, 255)
+    };
+    let (cx, cy) = (x + cell / 2.0, y + cell / 2.0);
+    draw_circle(cx, cy, cell * 0.45, Color::from_rgba(0, 40, 10, 255));
+    draw_circle_lines(cx, cy, cell * 0.45, 2.0, color);
+
+    let text = format!("{}/{}", solved, total);
+    let mut size = (cell * 0.4) as u16;
+    // Shrink long counts until they fit inside the ring
+    while size > 6 && measure_text_f(&text, font, size).width > cell * 0.8 {
+        size -= 1;
+    }
+    let dims = measure_text_f(&text, font, size);
+    draw_text_f(
+        &text,
+        cx - dims.width / 2.0,
+        cy + dims.offset_y / 2.0,
+        font,
+        size,
+        color,
+    );
+}
+
 fn render_dialogue(description: Option<&str>, dialogue_y: f32, font: &Font) {
     let dialogue_height = screen_height() - dialogue_y - BUTTON_BAR_HEIGHT - BOTTOM_SAFE_AREA;
 
@@ -525,7 +581,7 @@ fn grid_offset(game: &Game) -> (f32, f32) {
     (offset_x, offset_y)
 }
 
-fn dialogue_y(game: &Game) -> f32 {
+pub(crate) fn dialogue_y(game: &Game) -> f32 {
     let cell = cell_size(game);
     let grid_h = game.grid_height() as f32 * cell;
     let grid_bottom = PADDING + grid_h + PADDING;
@@ -538,7 +594,7 @@ pub(crate) fn button_bar_y() -> f32 {
     screen_height() - BUTTON_BAR_HEIGHT - BOTTOM_SAFE_AREA
 }
 
-fn button_labels(on_portal: bool, hints: InputHints) -> [(&'static str, ButtonAction); 4] {
+fn button_labels(on_portal: bool, hints: InputHints) -> [(&'static str, ButtonAction); 8] {
     use ControllerType::*;
     use InputHints::*;
 
@@ -558,6 +614,14 @@ fn button_labels(on_portal: bool, hints: InputHints) -> [(&'static str, ButtonAc
         Controller(Nintendo) => "Undo (Y)",
     };
 
+    let redo = match hints {
+        Keyboard => "Redo (Y)",
+        Touch => "Redo",
+        Controller(Xbox | Generic) => "Redo (RB)",
+        Controller(PlayStation) => "Redo (R1)",
+        Controller(Nintendo) => "Redo (R)",
+    };
+
     let stall = match (on_portal, hints) {
         (true, Keyboard) => "Enter (Space)",
         (false, Keyboard) => "Stall (Space)",
@@ -571,6 +635,30 @@ fn button_labels(on_portal: bool, hints: InputHints) -> [(&'static str, ButtonAc
         (false, Controller(Nintendo)) => "Stall (B)",
     };
 
+    let history = match hints {
+        Keyboard => "History (H)",
+        Touch => "History",
+        Controller(Xbox | Generic) => "History (View)",
+        Controller(PlayStation) => "History (Share)",
+        Controller(Nintendo) => "History (-)",
+    };
+
+    let levels = match hints {
+        Keyboard => "Levels (L)",
+        Touch => "Levels",
+        Controller(Xbox | Generic) => "Levels (RS)",
+        Controller(PlayStation) => "Levels (R3)",
+        Controller(Nintendo) => "Levels (RS)",
+    };
+
+    let settings = match hints {
+        Keyboard => "Settings (O)",
+        Touch => "Settings",
+        Controller(Xbox | Generic) => "Settings (LS)",
+        Controller(PlayStation) => "Settings (L3)",
+        Controller(Nintendo) => "Settings (LS)",
+    };
+
     let exit = match hints {
         Keyboard => "Exit (Esc)",
         Touch => "Exit",
@@ -582,7 +670,11 @@ fn button_labels(on_portal: bool, hints: InputHints) -> [(&'static str, ButtonAc
     [
         (reset, ButtonAction::Reset),
         (undo, ButtonAction::Undo),
+        (redo, ButtonAction::Redo),
+        (settings, ButtonAction::Settings),
         (stall, ButtonAction::Stall),
+        (history, ButtonAction::History),
+        (levels, ButtonAction::Levels),
         (exit, ButtonAction::Exit),
     ]
 }
@@ -592,28 +684,29 @@ fn button_rects(
     hints: InputHints,
     bar_y: f32,
     font: &Font,
-) -> [(f32, f32, f32, f32, ButtonAction); 4] {
+) -> [(f32, f32, f32, f32, ButtonAction); 8] {
     let buttons = button_labels(on_portal, hints);
 
-    // 2x2 grid: row 0 = buttons 0,1; row 1 = buttons 2,3
+    // Two rows: row 0 = reset, undo, redo, settings; row 1 = stall, history, levels, exit
     let row_height = BUTTON_HEIGHT + BUTTON_SPACING;
     let y_start = bar_y + (BUTTON_BAR_HEIGHT - 2.0 * BUTTON_HEIGHT - BUTTON_SPACING) / 2.0;
 
-    let mut rects = [(0.0, 0.0, 0.0, 0.0, ButtonAction::Reset); 4];
-    for row in 0..2 {
-        let row_buttons: Vec<_> = buttons[row * 2..(row + 1) * 2].to_vec();
+    let mut rects = [(0.0, 0.0, 0.0, 0.0, ButtonAction::Reset); 8];
+    for (row, range) in [0..4, 4..8].into_iter().enumerate() {
+        let start = range.start;
+        let row_buttons = &buttons[range];
         let row_width: f32 = row_buttons
             .iter()
             .map(|(label, _)| measure_text_f(label, font, 22).width + 20.0)
             .sum::<f32>()
-            + BUTTON_SPACING;
+            + BUTTON_SPACING * (row_buttons.len() - 1) as f32;
 
         let mut x = (screen_width() - row_width) / 2.0;
         let y = y_start + row as f32 * row_height;
 
         for (i, (label, action)) in row_buttons.iter().enumerate() {
             let w = measure_text_f(label, font, 22).width + 20.0;
-            rects[row * 2 + i] = (x, y, w, BUTTON_HEIGHT, *action);
+            rects[start + i] = (x, y, w, BUTTON_HEIGHT, *action);
             x += w + BUTTON_SPACING;
         }
     }
@@ -644,7 +737,11 @@ fn render_button_bar(ui: &UiState, is_playing: bool, hints: InputHints, bar_y: f
     let enabled_states = [
         (ButtonAction::Reset, ui.can_reset),
         (ButtonAction::Undo, ui.can_undo),
+        (ButtonAction::Redo, ui.can_redo),
+        (ButtonAction::Settings, true),
         (ButtonAction::Stall, is_playing),
+        (ButtonAction::History, ui.can_browse),
+        (ButtonAction::Levels, true),
         (ButtonAction::Exit, ui.can_exit),
     ];
 
@@ -697,7 +794,10 @@ pub(crate) fn button_at_position(
             let enabled = match action {
                 ButtonAction::Reset => ui.can_reset,
                 ButtonAction::Undo => ui.can_undo,
+                ButtonAction::Redo => ui.can_redo,
                 ButtonAction::Stall => is_playing,
+                ButtonAction::History => ui.can_browse,
+                ButtonAction::Levels | ButtonAction::Settings => true,
                 ButtonAction::Exit => ui.can_exit,
             };
             if enabled {
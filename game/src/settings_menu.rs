@@ -0,0 +1,259 @@
+use macroquad::prelude::*;
+use quad_gamepad::ControllerType;
+
+use crate::direction::Dir4;
+use crate::game::SPEEDS;
+use crate::input::Input;
+use crate::render::{InputHints, draw_text_f, measure_text_f};
+use crate::storage::{Settings, load_settings, save_settings};
+
+const LIST_TOP: f32 = 120.0;
+const LIST_WIDTH: f32 = 520.0;
+const ROW_HEIGHT: f32 = 48.0;
+const BUTTON_WIDTH: f32 = 140.0;
+const BUTTON_HEIGHT: f32 = 36.0;
+const BOTTOM_SAFE_AREA: f32 = 80.0;
+
+#[derive(Clone, Copy)]
+enum Setting {
+    MoveSpeed,
+    EffectSpeed,
+    Instant,
+}
+
+const ROWS: [Setting; 3] = [Setting::MoveSpeed, Setting::EffectSpeed, Setting::Instant];
+
+impl Setting {
+    fn label(self) -> &'static str {
+        match self {
+            Setting::MoveSpeed => "Move speed",
+            Setting::EffectSpeed => "Zap and explosion speed",
+            Setting::Instant => "Instant moves",
+        }
+    }
+}
+
+/// The closest speed in `SPEEDS` above or below `speed`, staying at the ends.
+fn step_speed(speed: f32, faster: bool) -> f32 {
+    if faster {
+        SPEEDS
+            .into_iter()
+            .find(|&s| s > speed)
+            .unwrap_or(SPEEDS[SPEEDS.len() - 1])
+    } else {
+        SPEEDS
+            .into_iter()
+            .rev()
+            .find(|&s| s < speed)
+            .unwrap_or(SPEEDS[0])
+    }
+}
+
+fn list_rect() -> (f32, f32) {
+    let w = LIST_WIDTH.min(screen_width() - 40.0);
+    ((screen_width() - w) / 2.0, w)
+}
+
+fn back_button_rect() -> Rect {
+    Rect::new(
+        (screen_width() - BUTTON_WIDTH) / 2.0,
+        screen_height() - BOTTOM_SAFE_AREA - BUTTON_HEIGHT,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+    )
+}
+
+/// Screen for changing preferences shared by every profile. Changes are saved straight away.
+pub(crate) struct SettingsMenu {
+    settings: Settings,
+    selected: usize,
+}
+
+impl SettingsMenu {
+    pub(crate) fn new() -> Self {
+        Self {
+            settings: load_settings(),
+            selected: 0,
+        }
+    }
+
+    /// Settings as currently chosen, to apply while the menu is still open.
+    pub(crate) fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    /// Change a setting to its next or previous value.
+    fn adjust(&mut self, row: usize, forward: bool) {
+        let animation = &mut self.settings.animation;
+        match ROWS[row] {
+            Setting::MoveSpeed => animation.moves = step_speed(animation.moves, forward),
+            Setting::EffectSpeed => animation.effects = step_speed(animation.effects, forward),
+            Setting::Instant => animation.instant = !animation.instant,
+        }
+        save_settings(&self.settings);
+    }
+
+    /// Apply keyboard, gamepad and tap input for a frame. Returns false once the menu is closed.
+    pub(crate) fn update(&mut self, inputs: &[Input], tap: Option<Vec2>) -> bool {
+        for &input in inputs {
+            match input {
+                Input::Move(Dir4::North) => self.selected = self.selected.saturating_sub(1),
+                Input::Move(Dir4::South) => self.selected = (self.selected + 1).min(ROWS.len() - 1),
+                Input::Move(Dir4::East) | Input::Confirm => self.adjust(self.selected, true),
+                Input::Move(Dir4::West) => self.adjust(self.selected, false),
+                Input::Exit | Input::Undo | Input::Settings => return false,
+                _ => {}
+            }
+        }
+
+        let Some(pos) = tap else {
+            return true;
+        };
+        if back_button_rect().contains(pos) {
+            return false;
+        }
+        // Tapping the left half of a row lowers its value, the right half raises it
+        let (x, w) = list_rect();
+        if pos.x >= x && pos.x < x + w && pos.y >= LIST_TOP {
+            let row = ((pos.y - LIST_TOP) / ROW_HEIGHT) as usize;
+            if row < ROWS.len() {
+                self.selected = row;
+                self.adjust(row, pos.x >= x + w / 2.0);
+            }
+        }
+        true
+    }
+
+    fn value(&self, setting: Setting) -> String {
+        let animation = &self.settings.animation;
+        match setting {
+            Setting::MoveSpeed => format!("{}×", animation.moves),
+            Setting::EffectSpeed => format!("{}×", animation.effects),
+            Setting::Instant if animation.instant => "On".to_string(),
+            Setting::Instant => "Off".to_string(),
+        }
+    }
+
+    fn hint(hints: InputHints) -> &'static str {
+        use ControllerType::*;
+        match hints {
+            InputHints::Keyboard => "↑/↓ to choose | ←/→ to change | Esc to go back",
+            InputHints::Touch => "Tap the left or right of a setting to change it",
+            InputHints::Controller(Xbox | Generic) => "D-pad to change | Menu to go back",
+            InputHints::Controller(PlayStation) => "D-pad to change | Options to go back",
+            InputHints::Controller(Nintendo) => "D-pad to change | + to go back",
+        }
+    }
+
+    pub(crate) fn render(&self, hints: InputHints, font: &Font) {
+        clear_background(Color::from_rgba(30, 30, 40, 255));
+        let center_x = screen_width() / 2.0;
+
+        // Title
+        let title = "SETTINGS";
+        let title_dims = measure_text_f(title, font, 48);
+        draw_text_f(
+            title,
+            center_x - title_dims.width / 2.0,
+            LIST_TOP - 40.0,
+            font,
+            48,
+            YELLOW,
+        );
+
+        let (list_x, list_w) = list_rect();
+        for (row, &setting) in ROWS.iter().enumerate() {
+            let y = LIST_TOP + row as f32 * ROW_HEIGHT;
+            let bg = if row == self.selected {
+                Color::from_rgba(80, 80, 100, 255)
+            } else {
+                Color::from_rgba(50, 50, 60, 255)
+            };
+            draw_rectangle(list_x, y, list_w, ROW_HEIGHT - 4.0, bg);
+            let text_y = y + ROW_HEIGHT / 2.0 + 8.0;
+            draw_text_f(setting.label(), list_x + 12.0, text_y, font, 26, WHITE);
+
+            // Speeds don't matter while moves are instant
+            let unused = self.settings.animation.instant
+                && matches!(setting, Setting::MoveSpeed | Setting::EffectSpeed);
+            let value = format!("< {} >", self.value(setting));
+            let value_dims = measure_text_f(&value, font, 24);
+            draw_text_f(
+                &value,
+                list_x + list_w - value_dims.width - 12.0,
+                text_y,
+                font,
+                24,
+                if unused { DARKGRAY } else { LIGHTGRAY },
+            );
+        }
+
+        // Back button
+        let rect = back_button_rect();
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::from_rgba(50, 50, 60, 255),
+        );
+        draw_rectangle_lines(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            1.0,
+            Color::from_rgba(70, 70, 85, 255),
+        );
+        let dims = measure_text_f("Back", font, 22);
+        draw_text_f(
+            "Back",
+            rect.x + (rect.w - dims.width) / 2.0,
+            rect.y + (rect.h + dims.height) / 2.0 - 2.0,
+            font,
+            22,
+            Color::from_rgba(220, 220, 230, 255),
+        );
+
+        // Hint
+        let hint = Self::hint(hints);
+        let hint_dims = measure_text_f(hint, font, 22);
+        draw_text_f(
+            hint,
+            center_x - hint_dims.width / 2.0,
+            screen_height() - BOTTOM_SAFE_AREA + 30.0,
+            font,
+            22,
+            GRAY,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::AnimationSpeed;
+
+    #[test]
+    fn speeds_step_through_choices_and_stop_at_the_ends() {
+        assert_eq!(step_speed(1.0, true), 1.5);
+        assert_eq!(step_speed(1.0, false), 0.75);
+        assert_eq!(step_speed(3.0, true), 3.0);
+        assert_eq!(step_speed(0.5, false), 0.5);
+        // A speed between choices moves to the choice on that side
+        assert_eq!(step_speed(1.2, false), 1.0);
+        assert_eq!(step_speed(1.2, true), 1.5);
+    }
+
+    #[test]
+    fn speeds_outside_the_choices_are_clamped() {
+        let speed = AnimationSpeed {
+            moves: 0.0,
+            effects: 100.0,
+            instant: false,
+        }
+        .clamped();
+        assert_eq!(speed.moves, 0.5);
+        assert_eq!(speed.effects, 3.0);
+    }
+}
@@ -8,6 +8,7 @@ pub struct Sprites {
     wall: Texture2D,
     portal_unvisited: Texture2D,
     portal_visited: Texture2D,
+    portal_locked: Texture2D,
     note: Texture2D,
     planks: Texture2D,
     spiderweb: Texture2D,
@@ -61,6 +62,7 @@ impl Sprites {
             wall: load_png(include_bytes!("../../assets/wall.png")),
             portal_unvisited: load_png(include_bytes!("../../assets/portal/unvisited.png")),
             portal_visited: load_png(include_bytes!("../../assets/portal/visited.png")),
+            portal_locked: load_png(include_bytes!("../../assets/portal/locked.png")),
             note: load_png(include_bytes!("../../assets/note.png")),
             planks: load_png(include_bytes!("../../assets/planks.png")),
             spiderweb: load_png(include_bytes!("../../assets/spiderweb.png")),
@@ -96,6 +98,10 @@ impl Sprites {
         }
     }
 
+    pub(crate) fn locked_portal(&self) -> &Texture2D {
+        &self.portal_locked
+    }
+
     pub(crate) fn note(&self) -> &Texture2D {
         &self.note
     }
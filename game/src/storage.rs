@@ -1,17 +1,249 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-pub(crate) use backend::save_completed_levels;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
-pub(crate) fn load_completed_levels() -> HashSet<String> {
-    backend::try_load_completed_levels()
-        .unwrap_or_default()
-        .into_iter()
-        .map(|s| strip_path_prefix(&s).to_string())
-        .collect()
+use crate::game::AnimationSpeed;
+use crate::levels;
+use crate::position::Position;
+
+const COMPLETED_LEVELS: &str = "completed_levels";
+const SESSION: &str = "session";
+const LEVEL_STATS: &str = "level_stats";
+const PROFILES: &str = "profiles";
+const SETTINGS: &str = "settings";
+const VERSION: &str = "version";
+
+/// Version of the save format. Saves without a version are 0.
+/// - 1: progress is stored per profile.
+/// - 2: completed levels are keyed by full level path rather than file stem.
+const SAVE_VERSION: u32 = 2;
+
+/// A level on the level stack and the moves played in it so far.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub(crate) struct SavedLevel {
+    pub(crate) level: String,
+    /// Moves in move notation (see `Action::format_moves`)
+    pub(crate) moves: String,
+    /// Where the player was put instead of the level's own start, e.g. on a portal when
+    /// jumping to a level inside this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) start: Option<Position>,
+}
+
+/// Where the player is: every level on the stack from the outermost to the current one.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub(crate) struct Session {
+    pub(crate) levels: Vec<SavedLevel>,
+}
+
+/// How a profile has played one level.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+pub(crate) struct LevelStats {
+    /// Times the level was started, by entering it or restarting.
+    pub(crate) attempts: u32,
+    pub(crate) restarts: u32,
+    pub(crate) undos: u32,
+    /// Moves and stalls taken, including ones later undone.
+    pub(crate) turns: u32,
+    /// Fewest moves in a winning attempt.
+    pub(crate) best_moves: Option<u32>,
+    /// Seconds spent in the level.
+    pub(crate) time: f64,
+}
+
+/// Preferences shared by every profile.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(crate) animation: AnimationSpeed,
+}
+
+/// A named save slot with its own completed levels and session.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub(crate) struct Profile {
+    pub(crate) id: u32,
+    pub(crate) name: String,
+}
+
+/// Every profile, in creation order, and which one was played last.
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct Profiles {
+    pub(crate) profiles: Vec<Profile>,
+    next_id: u32,
+    pub(crate) last_used: Option<u32>,
+}
+
+impl Profiles {
+    /// Load the profile list, first migrating saves written by older versions.
+    /// A list that can't be parsed is an error rather than empty, since a new list would reuse
+    /// the ids of the profiles in it and pick up their saves.
+    pub(crate) fn load() -> Result<Self, String> {
+        migrate();
+        Ok(try_load(PROFILES)?.unwrap_or_default())
+    }
+
+    pub(crate) fn save(&self) {
+        save(PROFILES, self);
+    }
+
+    pub(crate) fn get(&self, id: u32) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.id == id)
+    }
+
+    /// Add an empty profile and return its id.
+    pub(crate) fn create(&mut self, name: String) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.profiles.push(Profile { id, name });
+        id
+    }
+
+    pub(crate) fn rename(&mut self, id: u32, name: String) {
+        if let Some(profile) = self.profiles.iter_mut().find(|p| p.id == id) {
+            profile.name = name;
+        }
+    }
+
+    /// Remove a profile and everything saved for it.
+    pub(crate) fn delete(&mut self, id: u32) {
+        self.profiles.retain(|p| p.id != id);
+        if self.last_used == Some(id) {
+            self.last_used = None;
+        }
+        reset_profile(id);
+    }
+
+    /// A name for a new profile that no other profile has.
+    pub(crate) fn unused_name(&self) -> String {
+        (1..)
+            .map(|n| format!("Player {}", n))
+            .find(|name| self.profiles.iter().all(|p| &p.name != name))
+            .unwrap()
+    }
+}
+
+pub(crate) fn load_settings() -> Settings {
+    let settings: Settings = load(SETTINGS).unwrap_or_default();
+    Settings {
+        animation: settings.animation.clamped(),
+    }
+}
+
+pub(crate) fn save_settings(settings: &Settings) {
+    save(SETTINGS, settings);
+}
+
+/// Storage key for one of a profile's saves.
+fn profile_key(profile: u32, name: &str) -> String {
+    format!("profile_{}_{}", profile, name)
+}
+
+/// Forget a profile's completed levels, session and level stats.
+pub(crate) fn reset_profile(profile: u32) {
+    backend::remove(&profile_key(profile, COMPLETED_LEVELS));
+    backend::remove(&profile_key(profile, SESSION));
+    backend::remove(&profile_key(profile, LEVEL_STATS));
+}
+
+pub(crate) fn load_completed_levels(profile: u32) -> HashSet<String> {
+    load(&profile_key(profile, COMPLETED_LEVELS)).unwrap_or_default()
+}
+
+/// Completed levels of the profile played last, for tools that show progress.
+/// Unlike `Profiles::load`, this never migrates or otherwise changes the saves.
+pub(crate) fn last_used_completed_levels() -> HashSet<String> {
+    let Some(profile) = load::<Profiles>(PROFILES).and_then(|p| p.last_used) else {
+        return HashSet::new();
+    };
+    let completed = load_completed_levels(profile);
+    if load::<u32>(VERSION).unwrap_or(0) < 2 {
+        let level_names: Vec<&str> = levels::level_names().collect();
+        full_level_paths(completed, &level_names)
+    } else {
+        completed
+    }
+}
+
+pub(crate) fn save_completed_levels(profile: u32, completed: &HashSet<String>) {
+    save(&profile_key(profile, COMPLETED_LEVELS), completed);
+}
+
+pub(crate) fn load_session(profile: u32) -> Option<Session> {
+    load(&profile_key(profile, SESSION))
 }
 
-pub(crate) fn strip_path_prefix(s: &str) -> &str {
-    s.rsplit('/').next().unwrap_or(s)
+pub(crate) fn save_session(profile: u32, session: &Session) {
+    save(&profile_key(profile, SESSION), session);
+}
+
+/// Stats for every level the profile has played, by level path.
+pub(crate) fn load_level_stats(profile: u32) -> HashMap<String, LevelStats> {
+    load(&profile_key(profile, LEVEL_STATS)).unwrap_or_default()
+}
+
+pub(crate) fn save_level_stats(profile: u32, stats: &HashMap<String, LevelStats>) {
+    save(&profile_key(profile, LEVEL_STATS), stats);
+}
+
+/// Upgrade saves one version at a time to `SAVE_VERSION`.
+fn migrate() {
+    let version = load::<u32>(VERSION).unwrap_or(0);
+    if version >= SAVE_VERSION {
+        return;
+    }
+    if version < 1 {
+        migrate_to_profiles();
+    }
+    if version < 2 {
+        // Leave the version as it is to try again once the profile list is fixed
+        let Ok(profiles) = try_load::<Profiles>(PROFILES) else {
+            return;
+        };
+        let profiles = profiles.unwrap_or_default();
+        let level_names: Vec<&str> = levels::level_names().collect();
+        for profile in &profiles.profiles {
+            let key = profile_key(profile.id, COMPLETED_LEVELS);
+            if let Some(completed) = load::<HashSet<String>>(&key) {
+                save(&key, &full_level_paths(completed, &level_names));
+            }
+        }
+    }
+    save(VERSION, &SAVE_VERSION);
+}
+
+/// Move progress saved before there were profiles into a first profile.
+/// Saves from the first version with profiles have no version either, so keep an existing list.
+fn migrate_to_profiles() {
+    if backend::read(PROFILES).is_some() {
+        return;
+    }
+    let mut profiles = Profiles::default();
+    let id = profiles.create("Player 1".to_string());
+    for name in [COMPLETED_LEVELS, SESSION] {
+        if let Some(json) = backend::read(name) {
+            backend::write(&profile_key(id, name), &json);
+            backend::remove(name);
+        }
+    }
+    profiles.last_used = Some(id);
+    profiles.save();
+}
+
+/// Completed levels were once stored by file stem, so `old_levels/overstep` was just `overstep`.
+/// Expand each stem to every level path ending in it. Unknown names are kept as they are.
+fn full_level_paths(completed: HashSet<String>, level_names: &[&str]) -> HashSet<String> {
+    completed
+        .into_iter()
+        .flat_map(|name| {
+            let paths: Vec<String> = level_names
+                .iter()
+                .filter(|path| path.rsplit('/').next() == Some(name.as_str()))
+                .map(|path| path.to_string())
+                .collect();
+            if paths.is_empty() { vec![name] } else { paths }
+        })
+        .collect()
 }
 
 macro_rules! warn_err {
@@ -20,59 +252,130 @@ macro_rules! warn_err {
     };
 }
 
+fn save<T: Serialize>(name: &str, value: &T) {
+    if let Some(json) = warn_err!(
+        serde_json::to_string(value),
+        "Failed to serialize {}: {}",
+        name
+    ) {
+        backend::write(name, &json);
+    }
+}
+
+fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let s = backend::read(name)?;
+    warn_err!(serde_json::from_str(&s), "Failed to parse {}: {}", name)
+}
+
+/// Like `load`, but a save that can't be parsed is an error rather than missing.
+fn try_load<T: DeserializeOwned>(name: &str) -> Result<Option<T>, String> {
+    let Some(s) = backend::read(name) else {
+        return Ok(None);
+    };
+    serde_json::from_str(&s)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", name, e))
+}
+
 #[cfg(target_arch = "wasm32")]
 mod backend {
-    use super::*;
+    fn storage_key(name: &str) -> String {
+        format!("infestation_{}", name)
+    }
 
-    const STORAGE_KEY: &str = "infestation_completed_levels";
+    pub(super) fn write(name: &str, json: &str) {
+        quad_storage::STORAGE
+            .lock()
+            .unwrap()
+            .set(&storage_key(name), json);
+    }
 
-    pub(crate) fn save_completed_levels(completed: &HashSet<String>) {
-        let json = serde_json::to_string(completed).unwrap();
+    pub(super) fn read(name: &str) -> Option<String> {
         quad_storage::STORAGE
             .lock()
             .unwrap()
-            .set(STORAGE_KEY, &json);
+            .get(&storage_key(name))
     }
 
-    pub(super) fn try_load_completed_levels() -> Option<HashSet<String>> {
-        let s = quad_storage::STORAGE.lock().unwrap().get(STORAGE_KEY)?;
-        warn_err!(serde_json::from_str(&s), "Failed to parse {}: {}", s)
+    pub(super) fn remove(name: &str) {
+        quad_storage::STORAGE
+            .lock()
+            .unwrap()
+            .remove(&storage_key(name));
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 mod backend {
-    use super::*;
-    use std::fs::{create_dir_all, read_to_string, write};
+    use std::fs::{create_dir_all, read_to_string, remove_file, write as write_file};
     use std::path::PathBuf;
 
-    pub(crate) fn save_completed_levels(completed: &HashSet<String>) {
-        if let Some(path) = save_path() {
+    pub(super) fn write(name: &str, json: &str) {
+        if let Some(path) = save_path(name) {
             if let Some(parent) = path.parent() {
                 let _ = create_dir_all(parent);
             }
-            if let Ok(json) = serde_json::to_string(completed) {
-                let _ = write(path, json);
-            }
+            let _ = write_file(path, json);
         }
     }
 
-    pub(super) fn try_load_completed_levels() -> Option<HashSet<String>> {
-        let path = save_path()?;
-        let s = warn_err!(
+    pub(super) fn read(name: &str) -> Option<String> {
+        let path = save_path(name)?;
+        // A missing file just means nothing was saved yet
+        if !path.exists() {
+            return None;
+        }
+        warn_err!(
             read_to_string(&path),
             "Failed to read {}: {}",
             path.display()
-        )?;
-        warn_err!(
-            serde_json::from_str(&s),
-            "Failed to parse {}: {}",
-            path.display()
         )
     }
 
-    fn save_path() -> Option<PathBuf> {
+    pub(super) fn remove(name: &str) {
+        if let Some(path) = save_path(name) {
+            let _ = remove_file(path);
+        }
+    }
+
+    fn save_path(name: &str) -> Option<PathBuf> {
         let dirs = directories::ProjectDirs::from("", "dspyz", "InfestationGame")?;
-        Some(dirs.data_dir().join("completed_levels.json"))
+        Some(dirs.data_dir().join(format!("{}.json", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stripped_level_names_expand_to_full_paths() {
+        let levels = [
+            "world",
+            "overstep",
+            "old_levels/overstep",
+            "old_levels/old_levels",
+        ];
+        let completed = [
+            "overstep",
+            "old_levels",
+            "cyborg_rats/fakeout",
+            "deleted_level",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        let mut expanded: Vec<_> = full_level_paths(completed, &levels).into_iter().collect();
+        expanded.sort();
+        assert_eq!(
+            expanded,
+            [
+                "cyborg_rats/fakeout",
+                "deleted_level",
+                "old_levels/old_levels",
+                "old_levels/overstep",
+                "overstep",
+            ]
+        );
     }
 }